@@ -0,0 +1,65 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use appflowy_cloud::biz::workspace::publish_dup::duplicate_database_rows;
+use collab_database::database::gen_row_id;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use shared_entity::dto::publish_dto::PublishDatabaseData;
+
+#[allow(dead_code)]
+#[path = "../tests/workspace/published_data.rs"]
+mod published_data;
+
+/// Replicates the rows of a published database `times` times, assigning a duplicated row id
+/// to every published row.
+fn large_published_database_rows(
+  times: usize,
+) -> (HashMap<String, Vec<u8>>, Arc<HashMap<String, String>>) {
+  let pub_db_data = serde_json::from_slice::<PublishDatabaseData>(
+    &hex::decode(published_data::GRID_1_DB_DATA).unwrap(),
+  )
+  .unwrap();
+  let mut database_row_collabs = HashMap::new();
+  for i in 0..times {
+    for (pub_row_id, row_bin_data) in &pub_db_data.database_row_collabs {
+      database_row_collabs.insert(format!("{}-{}", pub_row_id, i), row_bin_data.clone());
+    }
+  }
+  let dup_row_ids = database_row_collabs
+    .keys()
+    .map(|pub_row_id| (pub_row_id.clone(), gen_row_id().to_string()))
+    .collect();
+  (database_row_collabs, Arc::new(dup_row_ids))
+}
+
+fn duplicate_database_rows_benchmark(c: &mut Criterion) {
+  let runtime = tokio::runtime::Builder::new_multi_thread()
+    .enable_all()
+    .build()
+    .unwrap();
+  let (database_row_collabs, dup_row_ids) = large_published_database_rows(100);
+  let new_db_id = uuid::Uuid::new_v4().to_string();
+
+  let mut group = c.benchmark_group("duplicate_database_rows");
+  group.throughput(Throughput::Elements(database_row_collabs.len() as u64));
+  for concurrency in [1, 4, 8] {
+    group.bench_with_input(
+      BenchmarkId::from_parameter(concurrency),
+      &concurrency,
+      |b, &concurrency| {
+        b.to_async(&runtime).iter(|| {
+          duplicate_database_rows(
+            black_box(database_row_collabs.clone()),
+            dup_row_ids.clone(),
+            new_db_id.clone(),
+            concurrency,
+          )
+        })
+      },
+    );
+  }
+  group.finish();
+}
+
+criterion_group!(benches, duplicate_database_rows_benchmark);
+criterion_main!(benches);
@@ -9,6 +9,7 @@ use app_error::AppError;
 use casbin::function_map::OperatorFunction;
 use casbin::rhai::{Dynamic, ImmutableString};
 use casbin::{CoreApi, DefaultModel, Enforcer, MgmtApi};
+use database::policy::select_policies_for_subject;
 use database_entity::dto::{AFAccessLevel, AFRole};
 
 use sqlx::PgPool;
@@ -25,8 +26,9 @@ pub enum AccessControlChange {
 
 /// Manages access control.
 ///
-/// Stores access control policies in the form `subject, object, role`
-/// where `subject` is `uid`, `object` is `oid`, and `role` is [AFAccessLevel] or [AFRole].
+/// Stores access control policies in the form `subject, object, role, source`
+/// where `subject` is `uid`, `object` is `oid`, `role` is [AFAccessLevel] or [AFRole], and
+/// `source` tells whether the policy is derived from membership or stored in `af_policy`.
 ///
 /// Roles are mapped to the corresponding actions that they are allowed to perform.
 /// `FullAccess` has write
@@ -37,6 +39,7 @@ pub enum AccessControlChange {
 /// according to the model defined.
 #[derive(Clone)]
 pub struct AccessControl {
+  pg_pool: PgPool,
   enforcer: Arc<AFEnforcer<NoEnforceGroup>>,
   #[allow(dead_code)]
   access_control_metrics: Arc<AccessControlMetrics>,
@@ -62,6 +65,7 @@ impl AccessControl {
     );
     let (change_tx, _) = broadcast::channel(1000);
     Ok(Self {
+      pg_pool,
       enforcer,
       access_control_metrics,
       change_tx,
@@ -93,6 +97,38 @@ impl AccessControl {
     Ok(())
   }
 
+  /// Reloads the policies of `subject` stored in `af_policy`, so that a write to them is enforced
+  /// without reloading every policy. Writes are also notified to every server through
+  /// [crate::casbin::notification::spawn_listen_on_policy_change].
+  pub async fn reload_policy(&self, subject: &str) -> Result<(), AppError> {
+    let policies = select_policies_for_subject(&self.pg_pool, subject)
+      .await?
+      .into_iter()
+      .map(|row| vec![row.subject, row.object, row.action])
+      .collect();
+    let (added, removed) = self
+      .enforcer
+      .replace_explicit_policies(subject, policies)
+      .await?;
+
+    // changes are only tracked for users
+    if let Ok(uid) = subject.parse::<i64>() {
+      for policy in added {
+        let oid = policy_object_id(&policy[POLICY_FIELD_INDEX_OBJECT]).to_string();
+        let _ = self
+          .change_tx
+          .send(AccessControlChange::UpdatePolicy { uid, oid });
+      }
+      for policy in removed {
+        let oid = policy_object_id(&policy[POLICY_FIELD_INDEX_OBJECT]).to_string();
+        let _ = self
+          .change_tx
+          .send(AccessControlChange::RemovePolicy { uid, oid });
+      }
+    }
+    Ok(())
+  }
+
   pub async fn enforce(
     &self,
     workspace_id: &str,
@@ -107,6 +143,14 @@ impl AccessControl {
   }
 }
 
+/// Returns the object id of a policy object, e.g. `123` for `collab::123`
+fn policy_object_id(policy_object: &str) -> &str {
+  policy_object
+    .split_once("::")
+    .map(|(_, object_id)| object_id)
+    .unwrap_or(policy_object)
+}
+
 ///
 /// ## Policy Definitions:
 /// - p1 = sub=uid, obj=object_id, act=role_id
@@ -118,6 +162,9 @@ impl AccessControl {
 /// - p3 = sub=guid, obj=object_id, act=access_level
 ///   - Defines the access level (`access_level`) a group (`guid`) has for an object (`object_id`).
 ///
+/// Every policy also stores its `src`, see [POLICY_SOURCE_MEMBERSHIP] and [POLICY_SOURCE_EXPLICIT].
+/// It is not matched against, so a policy is enforced the same whatever its source.
+///
 /// ## Role Definitions in Database:
 /// Roles and access levels are defined with the following mappings:
 /// - **Role "1" (Owner):** Can `delete`, `write`, and `read`.
@@ -166,7 +213,7 @@ pub const MODEL_CONF: &str = r###"
 r = sub, obj, act
 
 [policy_definition]
-p = sub, obj, act
+p = sub, obj, act, src
 
 [role_definition]
 g = _, _ # role and access level rule
@@ -222,13 +269,20 @@ pub fn cmp_role_or_level(r_act: ImmutableString, p_act: ImmutableString) -> Dyna
 }
 
 /// Represents the entity stored at the index of the access control policy.
-/// `subject_id, object_id, role/action`
+/// `subject_id, object_id, role/action, source`
 ///
-/// E.g. user1, collab::123, Owner
+/// E.g. user1, collab::123, Owner, m
 ///
 pub const POLICY_FIELD_INDEX_SUBJECT: usize = 0;
 pub const POLICY_FIELD_INDEX_OBJECT: usize = 1;
 pub const POLICY_FIELD_INDEX_ACTION: usize = 2;
+pub const POLICY_FIELD_INDEX_SOURCE: usize = 3;
+
+/// Source of the policies derived from workspace and collab membership.
+pub const POLICY_SOURCE_MEMBERSHIP: &str = "m";
+/// Source of the policies stored as is in `af_policy`.
+/// Kept apart from membership so that removing one never revokes an identical policy of the other.
+pub const POLICY_SOURCE_EXPLICIT: &str = "e";
 
 /// Represents the entity stored at the index of the grouping.
 /// `role, action`
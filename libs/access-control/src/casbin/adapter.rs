@@ -9,9 +9,12 @@ use casbin::Result;
 
 use database::collab::select_collab_member_access_level;
 use database::pg_row::AFCollabMemberAccessLevelRow;
+use database::pg_row::AFPolicyRow;
 use database::pg_row::AFWorkspaceMemberPermRow;
+use database::policy::select_policy_stream;
 use database::workspace::select_workspace_member_perm_stream;
 
+use super::access::{POLICY_SOURCE_EXPLICIT, POLICY_SOURCE_MEMBERSHIP};
 use crate::act::Acts;
 use futures_util::stream::BoxStream;
 use sqlx::PgPool;
@@ -20,7 +23,8 @@ use std::time::Instant;
 use tokio_stream::StreamExt;
 
 /// Implementation of [`casbin::Adapter`] for access control authorisation.
-/// Access control policies that are managed by workspace and collab CRUD.
+/// Access control policies that are managed by workspace and collab CRUD, and the ones stored in
+/// `af_policy`.
 pub struct PgAdapter {
   pg_pool: PgPool,
   access_control_metrics: Arc<AccessControlMetrics>,
@@ -48,6 +52,7 @@ async fn load_collab_policies(
         uid.to_string(),
         object_type.policy_object(),
         act.to_string(),
+        POLICY_SOURCE_MEMBERSHIP.to_string(),
       ]
       .to_vec();
       policies.push(policy);
@@ -83,9 +88,9 @@ async fn load_collab_policies(
 ///
 /// ```ignore
 /// [
-///   ["1", "workspace:123", "owner"],
-///   ["1", "workspace:123", "member"], // Implicit permission for owner
-///   ["1", "workspace:123", "guest"],  // Implicit permission for owner
+///   ["1", "workspace:123", "owner", "m"],
+///   ["1", "workspace:123", "member", "m"], // Implicit permission for owner
+///   ["1", "workspace:123", "guest", "m"],  // Implicit permission for owner
 /// ]
 /// ```
 ///
@@ -110,6 +115,7 @@ async fn load_workspace_policies(
         uid.to_string(),
         object_type.policy_object(),
         act.to_string(),
+        POLICY_SOURCE_MEMBERSHIP.to_string(),
       ];
       policies.push(policy);
     }
@@ -118,6 +124,24 @@ async fn load_workspace_policies(
   Ok(policies)
 }
 
+/// Loads the policies stored as is in `af_policy`, see [POLICY_SOURCE_EXPLICIT].
+async fn load_explicit_policies(
+  mut stream: BoxStream<'_, sqlx::Result<AFPolicyRow>>,
+) -> Result<Vec<Vec<String>>> {
+  let mut policies: Vec<Vec<String>> = Vec::new();
+
+  while let Some(Ok(row)) = stream.next().await {
+    policies.push(vec![
+      row.subject,
+      row.object,
+      row.action,
+      POLICY_SOURCE_EXPLICIT.to_string(),
+    ]);
+  }
+
+  Ok(policies)
+}
+
 #[async_trait]
 impl Adapter for PgAdapter {
   async fn load_policy(&mut self, model: &mut dyn Model) -> Result<()> {
@@ -134,6 +158,11 @@ impl Adapter for PgAdapter {
     // Policy definition `p` of type `p`. See `model.conf`
     model.add_policies("p", "p", collab_policies);
 
+    let explicit_policies = load_explicit_policies(select_policy_stream(&self.pg_pool)).await?;
+
+    // Policy definition `p` of type `p`. See `model.conf`
+    model.add_policies("p", "p", explicit_policies);
+
     self
       .access_control_metrics
       .record_load_all_policies_in_ms(start.elapsed().as_millis() as u64);
@@ -1,5 +1,6 @@
 use super::access::{
-  load_group_policies, AccessControlChange, POLICY_FIELD_INDEX_OBJECT, POLICY_FIELD_INDEX_SUBJECT,
+  load_group_policies, AccessControlChange, POLICY_FIELD_INDEX_OBJECT, POLICY_FIELD_INDEX_SOURCE,
+  POLICY_FIELD_INDEX_SUBJECT, POLICY_SOURCE_EXPLICIT, POLICY_SOURCE_MEMBERSHIP,
 };
 use crate::act::ActionVariant;
 use crate::entity::ObjectType;
@@ -9,6 +10,7 @@ use anyhow::anyhow;
 use app_error::AppError;
 use async_trait::async_trait;
 use casbin::{CoreApi, Enforcer, MgmtApi};
+use std::collections::HashSet;
 use std::sync::atomic::Ordering;
 use tokio::sync::RwLock;
 use tracing::{event, instrument, trace};
@@ -56,7 +58,14 @@ where
     let policies = act
       .policy_acts()
       .into_iter()
-      .map(|act| vec![uid.to_string(), obj.policy_object(), act.to_string()])
+      .map(|act| {
+        vec![
+          uid.to_string(),
+          obj.policy_object(),
+          act.to_string(),
+          POLICY_SOURCE_MEMBERSHIP.to_string(),
+        ]
+      })
       .collect::<Vec<Vec<_>>>();
     let number_of_updated_policies = policies.len();
 
@@ -91,6 +100,70 @@ where
       .await
   }
 
+  /// Replaces the explicit policies of `subject` with `policies`, given as
+  /// `subject, object, action`. Returns the policies that were added and the ones that were removed.
+  ///
+  /// Policies derived from membership are left as is, even the ones identical to an explicit policy.
+  pub async fn replace_explicit_policies(
+    &self,
+    subject: &str,
+    policies: Vec<Vec<String>>,
+  ) -> Result<(Vec<Vec<String>>, Vec<Vec<String>>), AppError> {
+    let mut enforcer = self.enforcer.write().await;
+    let new_policies = policies
+      .into_iter()
+      .map(|mut policy| {
+        policy.push(POLICY_SOURCE_EXPLICIT.to_string());
+        policy
+      })
+      .collect::<HashSet<_>>();
+    let old_policies = enforcer
+      .get_filtered_policy(POLICY_FIELD_INDEX_SUBJECT, vec![subject.to_string()])
+      .into_iter()
+      .filter(|p| p[POLICY_FIELD_INDEX_SOURCE] == POLICY_SOURCE_EXPLICIT)
+      .collect::<HashSet<_>>();
+    let added = new_policies
+      .difference(&old_policies)
+      .cloned()
+      .collect::<Vec<_>>();
+    let removed = old_policies
+      .difference(&new_policies)
+      .cloned()
+      .collect::<Vec<_>>();
+
+    trace!(
+      "[access control]: replace explicit policies of {}: add {:?}, remove {:?}",
+      subject,
+      added,
+      removed
+    );
+    // one at a time, since a batch is rejected as a whole if one of its policies is (not) present.
+    // Retrying after a failure is fine: adding or removing a policy twice is a no-op.
+    for policy in &removed {
+      enforcer
+        .remove_policy(policy.clone())
+        .await
+        .map_err(|e| AppError::Internal(anyhow!("fail to remove policy: {e:?}")))?;
+    }
+    for policy in &added {
+      enforcer
+        .add_policy(policy.clone())
+        .await
+        .map_err(|e| AppError::Internal(anyhow!("fail to add policy: {e:?}")))?;
+    }
+
+    let without_source = |policies: Vec<Vec<String>>| {
+      policies
+        .into_iter()
+        .map(|mut policy| {
+          policy.truncate(POLICY_FIELD_INDEX_SOURCE);
+          policy
+        })
+        .collect::<Vec<_>>()
+    };
+    Ok((without_source(added), without_source(removed)))
+  }
+
   /// 1. **Workspace Policy**: Initially, it checks if the user has permission at the workspace level. If the user
   ///    has permission to perform the action on the workspace, the function returns `true` without further checks.
   ///
@@ -223,9 +296,13 @@ async fn policies_for_subject_with_given_object<T: ToString>(
   let policies_related_to_object =
     enforcer.get_filtered_policy(POLICY_FIELD_INDEX_OBJECT, vec![object_type_id]);
 
+  // the explicit policies are only replaced by [AFEnforcer::replace_explicit_policies]
   policies_related_to_object
     .into_iter()
-    .filter(|p| p[POLICY_FIELD_INDEX_SUBJECT] == subject)
+    .filter(|p| {
+      p[POLICY_FIELD_INDEX_SUBJECT] == subject
+        && p[POLICY_FIELD_INDEX_SOURCE] == POLICY_SOURCE_MEMBERSHIP
+    })
     .collect::<Vec<_>>()
 }
 
@@ -240,7 +317,7 @@ impl EnforcerGroup for NoEnforceGroup {
 #[cfg(test)]
 mod tests {
   use crate::{
-    act::{Action, ActionVariant},
+    act::{Action, ActionVariant, Acts},
     casbin::{
       access::{casbin_model, cmp_role_or_level},
       enforcer::NoEnforceGroup,
@@ -842,4 +919,141 @@ mod tests {
       }
     }
   }
+
+  #[tokio::test]
+  async fn replace_explicit_policies_test() {
+    let enforcer = test_enforcer(NoEnforceGroup).await;
+    let uid = 1;
+    let workspace_id = "w1";
+    let object_1 = "o1";
+    let object_2 = "o2";
+    let policy = |object_id: &str, level: &AFAccessLevel| {
+      vec![
+        uid.to_string(),
+        ObjectType::Collab(object_id).policy_object(),
+        level.to_enforce_act().to_string(),
+      ]
+    };
+    let can_write = |object_id: &'static str| {
+      let enforcer = &enforcer;
+      async move {
+        enforcer
+          .enforce_policy(
+            workspace_id,
+            &uid,
+            ObjectType::Collab(object_id),
+            ActionVariant::FromAction(&Action::Write),
+          )
+          .await
+          .is_ok()
+      }
+    };
+
+    let (added, removed) = enforcer
+      .replace_explicit_policies(
+        &uid.to_string(),
+        vec![
+          policy(object_1, &AFAccessLevel::ReadAndWrite),
+          policy(object_2, &AFAccessLevel::ReadAndWrite),
+        ],
+      )
+      .await
+      .unwrap();
+    assert_eq!((added.len(), removed.len()), (2, 0));
+    assert!(can_write(object_1).await);
+    assert!(can_write(object_2).await);
+
+    // only the policies that are gone are removed
+    let (added, removed) = enforcer
+      .replace_explicit_policies(
+        &uid.to_string(),
+        vec![
+          policy(object_1, &AFAccessLevel::ReadAndWrite),
+          policy(object_2, &AFAccessLevel::ReadOnly),
+        ],
+      )
+      .await
+      .unwrap();
+    assert_eq!(added, vec![policy(object_2, &AFAccessLevel::ReadOnly)]);
+    assert_eq!(
+      removed,
+      vec![policy(object_2, &AFAccessLevel::ReadAndWrite)]
+    );
+    assert!(can_write(object_1).await);
+    assert!(!can_write(object_2).await);
+
+    enforcer
+      .replace_explicit_policies(&uid.to_string(), vec![])
+      .await
+      .unwrap();
+    assert!(!can_write(object_1).await);
+  }
+
+  #[tokio::test]
+  async fn explicit_and_membership_policies_are_kept_apart_test() {
+    let enforcer = test_enforcer(NoEnforceGroup).await;
+    let uid = 1;
+    let workspace_id = "w1";
+    let object_1 = "o1";
+    let explicit_policy = vec![
+      uid.to_string(),
+      ObjectType::Collab(object_1).policy_object(),
+      AFAccessLevel::ReadAndWrite.to_enforce_act().to_string(),
+    ];
+    let can_write = || {
+      let enforcer = &enforcer;
+      async move {
+        enforcer
+          .enforce_policy(
+            workspace_id,
+            &uid,
+            ObjectType::Collab(object_1),
+            ActionVariant::FromAction(&Action::Write),
+          )
+          .await
+          .is_ok()
+      }
+    };
+
+    // the same policy is granted by membership and stored explicitly
+    enforcer
+      .update_policy(
+        &uid,
+        ObjectType::Collab(object_1),
+        ActionVariant::FromAccessLevel(&AFAccessLevel::ReadAndWrite),
+      )
+      .await
+      .unwrap();
+    let (added, _) = enforcer
+      .replace_explicit_policies(&uid.to_string(), vec![explicit_policy.clone()])
+      .await
+      .unwrap();
+    assert_eq!(added, vec![explicit_policy.clone()]);
+
+    // removing the explicit policy keeps the membership one
+    enforcer
+      .replace_explicit_policies(&uid.to_string(), vec![])
+      .await
+      .unwrap();
+    assert!(can_write().await);
+
+    // removing the membership keeps the explicit policy
+    enforcer
+      .replace_explicit_policies(&uid.to_string(), vec![explicit_policy.clone()])
+      .await
+      .unwrap();
+    enforcer
+      .remove_policy(&uid, &ObjectType::Collab(object_1))
+      .await
+      .unwrap();
+    assert!(can_write().await);
+
+    // and the explicit policy is still known to be there
+    let (added, removed) = enforcer
+      .replace_explicit_policies(&uid.to_string(), vec![])
+      .await
+      .unwrap();
+    assert_eq!((added, removed), (vec![], vec![explicit_policy]));
+    assert!(!can_write().await);
+  }
 }
@@ -1,8 +1,10 @@
 use super::access::AccessControl;
 use crate::act::ActionVariant;
 use crate::entity::ObjectType;
+use database::pg_row::AFPolicyRow;
 use database_entity::dto::AFRole;
 use serde::Deserialize;
+use std::collections::HashSet;
 use tokio::sync::broadcast;
 use tracing::error;
 use tracing::log::warn;
@@ -57,6 +59,35 @@ pub fn spawn_listen_on_workspace_member_change(
   });
 }
 
+/// Reloads the policies of the subjects whose rows of `af_policy` changed. Runs on every server,
+/// including the one that wrote the change.
+///
+/// The trigger notifies once per row, so the notifications already received are drained
+/// before reloading, and a subject is reloaded once for all of them, e.g. when all of its rows
+/// are deleted.
+pub fn spawn_listen_on_policy_change(
+  mut listener: broadcast::Receiver<PolicyNotification>,
+  access_control: AccessControl,
+) {
+  tokio::spawn(async move {
+    while let Ok(change) = listener.recv().await {
+      let mut subjects = HashSet::new();
+      subjects.extend(change.subjects());
+      while let Ok(change) = listener.try_recv() {
+        subjects.extend(change.subjects());
+      }
+      for subject in subjects {
+        if let Err(err) = access_control.reload_policy(&subject).await {
+          error!(
+            "Failed to reload the policies of subject:{}, error: {}",
+            subject, err
+          );
+        }
+      }
+    }
+  });
+}
+
 #[allow(clippy::upper_case_acronyms)]
 #[derive(Deserialize, Clone, Debug)]
 pub enum WorkspaceMemberAction {
@@ -78,3 +109,15 @@ pub struct WorkspaceMemberRow {
   pub role_id: i64,
   pub workspace_id: Uuid,
 }
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct PolicyNotification {
+  pub old: Option<AFPolicyRow>,
+  pub new: Option<AFPolicyRow>,
+}
+
+impl PolicyNotification {
+  fn subjects(self) -> impl Iterator<Item = String> {
+    self.old.into_iter().chain(self.new).map(|row| row.subject)
+  }
+}
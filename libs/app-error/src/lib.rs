@@ -86,6 +86,11 @@ pub enum AppError {
   #[error("{0}")]
   SqlxError(String),
 
+  /// Serialization failure or deadlock, the transaction can be retried as is
+  #[cfg(feature = "sqlx_error")]
+  #[error("Transaction conflict:{0}")]
+  SqlxTransactionConflict(String),
+
   #[cfg(feature = "sqlx_error")]
   #[error("{desc}: {err}")]
   SqlxArgEncodingError {
@@ -173,6 +178,23 @@ pub enum AppError {
 
   #[error("There is an invalid character in the publish namespace: {character}")]
   CustomNamespaceInvalidCharacter { character: char },
+
+  #[error("Failed to decode {collab_type} collab {object_id}: {source}")]
+  CollabDecodeFailed {
+    object_id: String,
+    collab_type: String,
+    #[source]
+    source: Box<dyn StdError + 'static + Send + Sync>,
+  },
+
+  #[error("Invalid publish payload:{0}")]
+  InvalidPublishPayload(String),
+
+  #[error("Invalid policy object {object}, expected workspace::<uuid> or collab::<uuid>")]
+  InvalidPolicyObject { object: String },
+
+  #[error("View {view_id} is not published, it might have been unpublished")]
+  ViewNotPublished { view_id: String },
 }
 
 impl AppError {
@@ -192,6 +214,14 @@ impl AppError {
     matches!(self, AppError::UserUnAuthorized(_))
   }
 
+  pub fn is_transaction_conflict(&self) -> bool {
+    #[cfg(feature = "sqlx_error")]
+    if matches!(self, AppError::SqlxTransactionConflict(_)) {
+      return true;
+    }
+    false
+  }
+
   pub fn code(&self) -> ErrorCode {
     match self {
       AppError::Ok => ErrorCode::Ok,
@@ -218,6 +248,8 @@ impl AppError {
       #[cfg(feature = "sqlx_error")]
       AppError::SqlxError(_) => ErrorCode::SqlxError,
       #[cfg(feature = "sqlx_error")]
+      AppError::SqlxTransactionConflict(_) => ErrorCode::SqlxError,
+      #[cfg(feature = "sqlx_error")]
       AppError::SqlxArgEncodingError { .. } => ErrorCode::SqlxArgEncodingError,
       #[cfg(feature = "validation_error")]
       AppError::ValidatorError(_) => ErrorCode::InvalidRequest,
@@ -249,6 +281,10 @@ impl AppError {
       AppError::CustomNamespaceInvalidCharacter { .. } => {
         ErrorCode::CustomNamespaceInvalidCharacter
       },
+      AppError::CollabDecodeFailed { .. } => ErrorCode::CollabDecodeFailed,
+      AppError::InvalidPublishPayload(_) => ErrorCode::InvalidPublishPayload,
+      AppError::InvalidPolicyObject { .. } => ErrorCode::InvalidPolicyObject,
+      AppError::ViewNotPublished { .. } => ErrorCode::ViewNotPublished,
     }
   }
 }
@@ -283,6 +319,10 @@ impl From<sqlx::Error> for AppError {
       sqlx::Error::RowNotFound => {
         AppError::RecordNotFound(format!("Record not exist in db. {})", msg))
       },
+      // serialization_failure and deadlock_detected
+      sqlx::Error::Database(err) if matches!(err.code().as_deref(), Some("40001" | "40P01")) => {
+        AppError::SqlxTransactionConflict(msg)
+      },
       _ => AppError::SqlxError(msg),
     }
   }
@@ -388,6 +428,10 @@ pub enum ErrorCode {
   PublishNameInvalidCharacter = 1051,
   PublishNameTooLong = 1052,
   CustomNamespaceInvalidCharacter = 1053,
+  CollabDecodeFailed = 1054,
+  InvalidPublishPayload = 1055,
+  InvalidPolicyObject = 1056,
+  ViewNotPublished = 1057,
 }
 
 impl ErrorCode {
@@ -942,6 +942,8 @@ impl TestClient {
         &PublishedDuplicate {
           published_view_id: src_view_id.to_string(),
           dest_view_id: dest_view_id.to_string(),
+          strategy: Default::default(),
+          run_async: false,
         },
       )
       .await
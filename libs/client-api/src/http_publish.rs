@@ -1,11 +1,14 @@
 use bytes::Bytes;
 use client_api_entity::workspace_dto::PublishInfoView;
-use client_api_entity::{workspace_dto::PublishedDuplicate, PublishInfo, UpdatePublishNamespace};
+use client_api_entity::workspace_dto::{
+  DuplicationStatus, PublishedDuplicate, PublishedDuplicateJob,
+};
 use client_api_entity::{
   CreateGlobalCommentParams, CreateReactionParams, DeleteGlobalCommentParams, DeleteReactionParams,
   GetReactionQueryParams, GlobalComments, PatchPublishedCollab, PublishInfoMeta, Reactions,
   UpdateDefaultPublishView,
 };
+use client_api_entity::{PublishInfo, UpdatePublishNamespace};
 use reqwest::Method;
 use shared_entity::response::{AppResponse, AppResponseError};
 use tracing::instrument;
@@ -396,7 +399,56 @@ impl Client {
       .send()
       .await?;
     log_request_id(&resp);
-    AppResponse::<()>::from_response(resp).await?.into_error()
+    AppResponse::<PublishedDuplicateJob>::from_response(resp)
+      .await?
+      .into_error()
+  }
+
+  /// Queues the duplication on the server and returns the job id immediately.
+  /// Use [Client::get_duplication_status] to poll the progress.
+  pub async fn duplicate_published_to_workspace_async(
+    &self,
+    workspace_id: &str,
+    publish_duplicate: &PublishedDuplicate,
+  ) -> Result<String, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/published-duplicate",
+      self.base_url, workspace_id
+    );
+    let publish_duplicate = PublishedDuplicate {
+      run_async: true,
+      ..publish_duplicate.clone()
+    };
+    let resp = self
+      .http_client_with_auth(Method::POST, &url)
+      .await?
+      .json(&publish_duplicate)
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<PublishedDuplicateJob>::from_response(resp)
+      .await?
+      .into_data()
+      .map(|job| job.job_id)
+  }
+
+  pub async fn get_duplication_status(
+    &self,
+    job_id: &str,
+  ) -> Result<DuplicationStatus, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/published-duplicate/{}",
+      self.base_url, job_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::GET, &url)
+      .await?
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<DuplicationStatus>::from_response(resp)
+      .await?
+      .into_data()
   }
 
   pub async fn get_published_view_reactions(
@@ -1,4 +1,5 @@
 pub mod dto;
 pub mod error;
 pub mod file_dto;
+pub mod policy;
 mod util;
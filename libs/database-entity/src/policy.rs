@@ -0,0 +1,37 @@
+/// Represents the object type that is stored in the access control policy.
+#[derive(Debug)]
+pub enum ObjectType<'id> {
+  /// Stored as `workspace::<uuid>`
+  Workspace(&'id str),
+  /// Stored as `collab::<uuid>`
+  Collab(&'id str),
+}
+
+impl<'id> ObjectType<'id> {
+  const WORKSPACE_PREFIX: &'static str = "workspace::";
+  const COLLAB_PREFIX: &'static str = "collab::";
+
+  /// Parses an object stored in the access control policy, the inverse of [Self::policy_object].
+  pub fn from_policy_object(object: &'id str) -> Option<Self> {
+    if let Some(id) = object.strip_prefix(Self::WORKSPACE_PREFIX) {
+      return Some(ObjectType::Workspace(id));
+    }
+    object
+      .strip_prefix(Self::COLLAB_PREFIX)
+      .map(ObjectType::Collab)
+  }
+
+  pub fn policy_object(&self) -> String {
+    match self {
+      ObjectType::Collab(s) => format!("{}{}", Self::COLLAB_PREFIX, s),
+      ObjectType::Workspace(s) => format!("{}{}", Self::WORKSPACE_PREFIX, s),
+    }
+  }
+
+  pub fn object_id(&self) -> &str {
+    match self {
+      ObjectType::Collab(s) => s,
+      ObjectType::Workspace(s) => s,
+    }
+  }
+}
@@ -86,10 +86,10 @@ pub async fn insert_into_af_collab(
         )
         .execute(tx.deref_mut())
         .await.map_err(|err| {
-          AppError::Internal(anyhow!(
-            "Update af_collab failed: workspace_id:{}, uid:{}, object_id:{}, collab_type:{}. error: {:?}",
-            workspace_id, uid, params.object_id, params.collab_type, err,
-          ))
+          af_collab_write_failed(format!(
+            "Update af_collab failed: workspace_id:{}, uid:{}, object_id:{}, collab_type:{}",
+            workspace_id, uid, params.object_id, params.collab_type,
+          ), err)
         })?;
       } else {
         return Err(AppError::Internal(anyhow!(
@@ -128,13 +128,13 @@ pub async fn insert_into_af_collab(
       .execute(tx.deref_mut())
       .await
       .map_err(|err| {
-        AppError::Internal(anyhow!(
-          "Insert af_collab_member failed: {}:{}:{}. error details:{:?}",
-          uid,
-          params.object_id,
-          permission_id,
-          err
-        ))
+        af_collab_write_failed(
+          format!(
+            "Insert af_collab_member failed: {}:{}:{}",
+            uid, params.object_id, permission_id,
+          ),
+          err,
+        )
       })?;
 
       sqlx::query!(
@@ -150,10 +150,10 @@ pub async fn insert_into_af_collab(
       )
       .execute(tx.deref_mut())
       .await.map_err(|err| {
-        AppError::Internal(anyhow!(
-          "Insert new af_collab failed: workspace_id:{}, uid:{}, object_id:{}, collab_type:{}. payload len:{} error: {:?}",
-         workspace_id, uid, params.object_id, params.collab_type, params.encoded_collab_v1.len(), err,
-        ))
+        af_collab_write_failed(format!(
+          "Insert new af_collab failed: workspace_id:{}, uid:{}, object_id:{}, collab_type:{}. payload len:{}",
+         workspace_id, uid, params.object_id, params.collab_type, params.encoded_collab_v1.len(),
+        ), err)
       })?;
     },
   }
@@ -161,6 +161,18 @@ pub async fn insert_into_af_collab(
   Ok(())
 }
 
+/// Describes a failed write with `context`. Transaction conflicts are kept as
+/// [AppError::SqlxTransactionConflict], so that the caller can still retry the transaction.
+fn af_collab_write_failed(context: String, err: sqlx::Error) -> AppError {
+  let details = format!("{:?}", err);
+  match AppError::from(err) {
+    AppError::SqlxTransactionConflict(msg) => {
+      AppError::SqlxTransactionConflict(format!("{}: {}", context, msg))
+    },
+    _ => AppError::Internal(anyhow!("{}. error: {}", context, details)),
+  }
+}
+
 /// Inserts or updates multiple collaboration records for a specific user in bulk. It assumes you are the
 /// owner of the workspace.
 ///
@@ -774,6 +786,18 @@ pub async fn is_collab_exists<'a, E: Executor<'a, Database = Postgres>>(
   transform_record_not_found_error(result)
 }
 
+/// Returns the id of the workspace the collab with `oid` belongs to, or None if it doesn't exist.
+#[inline]
+pub async fn select_collab_workspace_id<'a, E: Executor<'a, Database = Postgres>>(
+  oid: &str,
+  executor: E,
+) -> Result<Option<Uuid>, sqlx::Error> {
+  sqlx::query_scalar("SELECT workspace_id FROM af_collab WHERE oid = $1")
+    .bind(oid)
+    .fetch_optional(executor)
+    .await
+}
+
 pub async fn select_workspace_database_oid<'a, E: Executor<'a, Database = Postgres>>(
   executor: E,
   workspace_id: &Uuid,
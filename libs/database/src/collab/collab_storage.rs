@@ -124,11 +124,14 @@ pub trait CollabStorage: Send + Sync + 'static {
   /// # Arguments
   /// * `object_id` - The ID of the collaboration object.
   /// * `collab_messages` - The list of collab messages to broadcast.
+  ///
+  /// # Returns
+  /// * `Result<bool>` - `false` if the delivery to the connected clients could not be confirmed.
   async fn broadcast_encode_collab(
     &self,
     object_id: String,
     collab_messages: Vec<ClientCollabMessage>,
-  ) -> Result<(), AppError>;
+  ) -> Result<bool, AppError>;
 
   async fn batch_get_collab(
     &self,
@@ -239,7 +242,7 @@ where
     &self,
     object_id: String,
     collab_messages: Vec<ClientCollabMessage>,
-  ) -> Result<(), AppError> {
+  ) -> Result<bool, AppError> {
     self
       .as_ref()
       .broadcast_encode_collab(object_id, collab_messages)
@@ -6,6 +6,7 @@ pub mod history;
 pub mod index;
 pub mod listener;
 pub mod pg_row;
+pub mod policy;
 pub mod publish;
 pub mod resource_usage;
 pub mod template;
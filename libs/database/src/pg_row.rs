@@ -188,6 +188,14 @@ pub struct AFCollabMemberAccessLevelRow {
   pub access_level: AFAccessLevel,
 }
 
+/// Row of `af_policy`, a policy in the casbin form `subject, object, action`
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct AFPolicyRow {
+  pub subject: String,
+  pub object: String,
+  pub action: String,
+}
+
 #[derive(FromRow, Clone, Debug, Serialize, Deserialize)]
 pub struct AFCollabMemberRow {
   pub uid: i64,
@@ -309,6 +317,22 @@ impl From<AFGlobalCommentRow> for GlobalComment {
   }
 }
 
+/// A comment on a published view together with its author, used for exporting comment
+/// activity across all published views.
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct RecentCommentEvent {
+  pub comment_id: Uuid,
+  pub view_id: Uuid,
+  pub content: String,
+  pub reply_comment_id: Option<Uuid>,
+  pub created_at: DateTime<Utc>,
+  pub updated_at: DateTime<Utc>,
+  pub is_deleted: bool,
+  /// None if the author has been removed
+  pub author_uuid: Option<Uuid>,
+  pub author_name: Option<String>,
+}
+
 pub struct AFReactionRow {
   pub reaction_type: String,
   pub react_users: Vec<AFWebUserColumn>,
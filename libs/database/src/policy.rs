@@ -0,0 +1,100 @@
+use app_error::AppError;
+use database_entity::policy::ObjectType;
+use futures_util::stream::BoxStream;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::pg_row::AFPolicyRow;
+
+pub fn select_policy_stream(pg_pool: &PgPool) -> BoxStream<'_, sqlx::Result<AFPolicyRow>> {
+  sqlx::query_as::<_, AFPolicyRow>("SELECT subject, object, action FROM af_policy").fetch(pg_pool)
+}
+
+pub async fn select_policies_for_subject(
+  pg_pool: &PgPool,
+  subject: &str,
+) -> Result<Vec<AFPolicyRow>, AppError> {
+  let policies = sqlx::query_as::<_, AFPolicyRow>(
+    r#"
+      SELECT subject, object, action
+      FROM af_policy
+      WHERE subject = $1
+    "#,
+  )
+  .bind(subject)
+  .fetch_all(pg_pool)
+  .await?;
+  Ok(policies)
+}
+
+/// Inserts the policy `(subject, object, action)`, does nothing if it already exists.
+/// `object` must be in one of the forms used by the access control: `workspace::<uuid>` or
+/// `collab::<uuid>`.
+pub async fn upsert_policy(
+  pg_pool: &PgPool,
+  subject: &str,
+  object: &str,
+  action: &str,
+) -> Result<(), AppError> {
+  validate_policy_object(object)?;
+  sqlx::query(
+    r#"
+      INSERT INTO af_policy (subject, object, action)
+      VALUES ($1, $2, $3)
+      ON CONFLICT (subject, object, action) DO NOTHING
+    "#,
+  )
+  .bind(subject)
+  .bind(object)
+  .bind(action)
+  .execute(pg_pool)
+  .await?;
+  Ok(())
+}
+
+/// Deletes the policy `(subject, object, action)`. Returns false if there was no such policy.
+pub async fn delete_policy(
+  pg_pool: &PgPool,
+  subject: &str,
+  object: &str,
+  action: &str,
+) -> Result<bool, AppError> {
+  let res = sqlx::query(
+    r#"
+      DELETE FROM af_policy
+      WHERE subject = $1 AND object = $2 AND action = $3
+    "#,
+  )
+  .bind(subject)
+  .bind(object)
+  .bind(action)
+  .execute(pg_pool)
+  .await?;
+  Ok(res.rows_affected() > 0)
+}
+
+/// Deletes all policies of `subject`, e.g. when the user is deleted.
+/// Returns the number of deleted policies.
+pub async fn delete_policies_for_subject(pg_pool: &PgPool, subject: &str) -> Result<u64, AppError> {
+  let res = sqlx::query(
+    r#"
+      DELETE FROM af_policy
+      WHERE subject = $1
+    "#,
+  )
+  .bind(subject)
+  .execute(pg_pool)
+  .await?;
+  Ok(res.rows_affected())
+}
+
+fn validate_policy_object(object: &str) -> Result<(), AppError> {
+  match ObjectType::from_policy_object(object)
+    .map(|object_type| Uuid::parse_str(object_type.object_id()))
+  {
+    Some(Ok(_)) => Ok(()),
+    _ => Err(AppError::InvalidPolicyObject {
+      object: object.to_string(),
+    }),
+  }
+}
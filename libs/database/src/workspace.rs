@@ -12,7 +12,7 @@ use uuid::Uuid;
 use crate::pg_row::{
   AFGlobalCommentRow, AFImportTask, AFPermissionRow, AFReactionRow, AFUserProfileRow,
   AFWebUserColumn, AFWorkspaceInvitationMinimal, AFWorkspaceMemberPermRow, AFWorkspaceMemberRow,
-  AFWorkspaceRow,
+  AFWorkspaceRow, RecentCommentEvent,
 };
 use crate::user::select_uid_from_email;
 use app_error::AppError;
@@ -1110,6 +1110,47 @@ pub async fn select_comments_for_published_view_ordered_by_recency<
   Ok(comments)
 }
 
+/// Returns the comments of all published views created within `from..=to`, ordered by
+/// creation time. Deleted comments are only returned when `include_deleted` is true.
+pub async fn select_comments_in_range<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  from: &DateTime<Utc>,
+  to: &DateTime<Utc>,
+  limit: i64,
+  offset: i64,
+  include_deleted: bool,
+) -> Result<Vec<RecentCommentEvent>, AppError> {
+  let query = r#"
+    SELECT
+      avc.comment_id,
+      avc.view_id,
+      avc.content,
+      avc.reply_comment_id,
+      avc.created_at,
+      avc.updated_at,
+      avc.is_deleted,
+      au.uuid AS author_uuid,
+      au.name AS author_name
+    FROM af_published_view_comment avc
+    LEFT OUTER JOIN af_user au ON avc.created_by = au.uid
+    WHERE avc.created_at >= $1
+      AND avc.created_at <= $2
+      AND ($3 OR NOT avc.is_deleted)
+    ORDER BY avc.created_at ASC, avc.comment_id ASC
+    LIMIT $4
+    OFFSET $5
+  "#;
+  let comments = sqlx::query_as::<_, RecentCommentEvent>(query)
+    .bind(from)
+    .bind(to)
+    .bind(include_deleted)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(executor)
+    .await?;
+  Ok(comments)
+}
+
 pub async fn insert_comment_to_published_view<'a, E: Executor<'a, Database = Postgres>>(
   executor: E,
   view_id: &Uuid,
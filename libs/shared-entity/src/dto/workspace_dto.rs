@@ -159,6 +159,49 @@ pub struct PageCollab {
 pub struct PublishedDuplicate {
   pub published_view_id: String,
   pub dest_view_id: String,
+  #[serde(default)]
+  pub strategy: DuplicationStrategy,
+  /// If true, the duplication is queued and a [PublishedDuplicateJob] is returned immediately.
+  /// Use the job id to poll the [DuplicationStatus].
+  #[serde(default)]
+  pub run_async: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublishedDuplicateJob {
+  pub job_id: String,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicationState {
+  Queued,
+  Running,
+  Done,
+  Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicationStatus {
+  pub state: DuplicationState,
+  /// Between 0.0 and 1.0, the fraction of the duplicated collabs written so far
+  pub progress: f32,
+  /// Id of the duplicated root view, available once the duplication is done
+  pub root_view_id: Option<String>,
+  /// Reason of the failure, available if the duplication failed
+  pub error: Option<String>,
+}
+
+/// Determines how databases embedded in a duplicated document are handled.
+#[derive(Default, Eq, PartialEq, Debug, Copy, Clone, Serialize_repr, Deserialize_repr)]
+#[repr(u8)]
+pub enum DuplicationStrategy {
+  /// Embedded databases are copied into new, independent databases.
+  #[default]
+  Deep = 0,
+  /// Embedded databases are not copied, the duplicated document keeps pointing at the
+  /// original database. Only allowed if the user can access that database.
+  ShallowLinkDatabases = 1,
 }
 
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
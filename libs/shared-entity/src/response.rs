@@ -162,6 +162,11 @@ impl AppResponseError {
   pub fn is_record_not_found(&self) -> bool {
     matches!(self.code, ErrorCode::RecordNotFound)
   }
+
+  /// The view exists or existed, but cannot be accessed because it is not published
+  pub fn is_view_not_published(&self) -> bool {
+    matches!(self.code, ErrorCode::ViewNotPublished)
+  }
 }
 
 impl<T> From<T> for AppResponseError
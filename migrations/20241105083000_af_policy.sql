@@ -0,0 +1,11 @@
+-- Access control policies that are managed explicitly by admins, in addition to the ones
+-- derived from workspace and collab membership. Columns follow the casbin `p = sub, obj, act`.
+CREATE TABLE IF NOT EXISTS af_policy (
+  subject    TEXT NOT NULL,
+  object     TEXT NOT NULL,
+  action     TEXT NOT NULL,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+  -- also serves the lookup of all policies of a subject
+  PRIMARY KEY (subject, object, action)
+);
@@ -0,0 +1,24 @@
+-- Listener for af_policy table, so that every server reloads the policies of the changed subject
+CREATE OR REPLACE FUNCTION notify_af_policy_change() RETURNS trigger AS $$
+DECLARE
+    payload TEXT;
+BEGIN
+    payload := json_build_object(
+            'old', row_to_json(OLD),
+            'new', row_to_json(NEW),
+            'action_type', TG_OP
+            )::text;
+
+    PERFORM pg_notify('af_policy_channel', payload);
+    -- Return the new row state for INSERT/UPDATE, and the old state for DELETE.
+    IF TG_OP = 'DELETE' THEN
+        RETURN OLD;
+    ELSE
+        RETURN NEW;
+    END IF;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE TRIGGER af_policy_change_trigger
+    AFTER INSERT OR UPDATE OR DELETE ON af_policy
+    FOR EACH ROW EXECUTE FUNCTION notify_af_policy_change();
@@ -19,6 +19,7 @@ use tracing::info;
 use crate::actix_ws::server::RealtimeServerActor;
 use crate::collab::access_control::CollabStorageAccessControlImpl;
 use access_control::casbin::access::AccessControl;
+use access_control::casbin::notification::spawn_listen_on_policy_change;
 use appflowy_ai_client::client::AppFlowyAIClient;
 
 use crate::api::{collab_scope, ws_scope};
@@ -113,6 +114,10 @@ pub async fn init_state(config: &Config, rt_cmd_tx: CLCommandSender) -> Result<A
   let pg_listeners = Arc::new(PgListeners::new(&pg_pool).await?);
   let access_control =
     AccessControl::new(pg_pool.clone(), metrics.access_control_metrics.clone()).await?;
+  spawn_listen_on_policy_change(
+    pg_listeners.subscribe_policy_change(),
+    access_control.clone(),
+  );
   // let collab_member_listener = pg_listeners.subscribe_collab_member_change();
   // let workspace_member_listener = pg_listeners.subscribe_workspace_member_change();
   // spawn_listen_on_workspace_member_change(workspace_member_listener, access_control.clone());
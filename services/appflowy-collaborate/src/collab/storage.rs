@@ -37,6 +37,10 @@ use crate::state::RedisConnectionManager;
 
 pub type CollabAccessControlStorage = CollabStorageImpl<CollabStorageAccessControlImpl>;
 
+/// How long to wait for the realtime server to confirm a broadcast. Longer than the time a group
+/// is given to apply server messages, so that an unconfirmed broadcast is reported by the group.
+const BROADCAST_CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(10);
+
 /// A wrapper around the actual storage implementation that provides access control and caching.
 #[derive(Clone)]
 pub struct CollabStorageImpl<AC> {
@@ -218,6 +222,55 @@ where
     });
     Ok(())
   }
+
+  /// Like [CollabStorage::insert_new_collab_with_transaction], but only writes to `transaction`:
+  /// nothing is left behind if the transaction is rolled back. Call
+  /// [Self::finish_committed_new_collab] once the transaction is committed.
+  pub async fn insert_new_collab_to_disk_with_transaction(
+    &self,
+    workspace_id: &str,
+    uid: &i64,
+    params: &CollabParams,
+    transaction: &mut Transaction<'_, sqlx::Postgres>,
+    action_description: &str,
+  ) -> AppResult<()> {
+    params.validate()?;
+    self
+      .check_write_workspace_permission(workspace_id, uid)
+      .await?;
+    match timeout(
+      Duration::from_secs(120),
+      self
+        .cache
+        .insert_encode_collab_to_disk(workspace_id, uid, params.clone(), transaction),
+    )
+    .await
+    {
+      Ok(result) => result,
+      Err(_) => {
+        error!(
+          "Timeout waiting for action completed: {}",
+          action_description
+        );
+        Err(AppError::RequestTimeout(action_description.to_string()))
+      },
+    }
+  }
+
+  /// Grants `uid` full access to a collab written by
+  /// [Self::insert_new_collab_to_disk_with_transaction] and caches it, once its transaction is
+  /// committed.
+  pub async fn finish_committed_new_collab(
+    &self,
+    uid: &i64,
+    params: &CollabParams,
+  ) -> AppResult<()> {
+    self
+      .access_control
+      .update_policy(uid, &params.object_id, AFAccessLevel::FullAccess)
+      .await?;
+    self.cache.insert_encode_collab_to_mem(params).await
+  }
 }
 
 #[async_trait]
@@ -488,7 +541,7 @@ where
     &self,
     object_id: String,
     collab_messages: Vec<ClientCollabMessage>,
-  ) -> Result<(), AppError> {
+  ) -> Result<bool, AppError> {
     let (sender, recv) = tokio::sync::oneshot::channel();
 
     self
@@ -506,16 +559,21 @@ where
         ))
       })?;
 
-    match recv.await {
-      Ok(res) =>
-        if let Err(err) = res {
-          error!("Failed to broadcast encode collab: {}", err);
-        }
-      ,
+    match timeout(BROADCAST_CONFIRMATION_TIMEOUT, recv).await {
+      Ok(Ok(Ok(delivered))) => Ok(delivered),
+      Ok(Ok(Err(err))) => {
+        error!("Failed to broadcast encode collab: {}", err);
+        Ok(false)
+      },
       // caller may have dropped the receiver
-      Err(err) => warn!("Failed to receive response from realtime server: {}", err),
+      Ok(Err(err)) => {
+        warn!("Failed to receive response from realtime server: {}", err);
+        Ok(false)
+      },
+      Err(_) => {
+        warn!("Timeout waiting for the realtime server to confirm the broadcast");
+        Ok(false)
+      },
     }
-
-    Ok(())
   }
 }
@@ -13,6 +13,7 @@ use futures::StreamExt;
 use std::{
   collections::HashMap,
   sync::{Arc, Weak},
+  time::Duration,
 };
 use tracing::error;
 
@@ -33,10 +34,14 @@ pub enum CollaborationCommand {
   ServerSendCollabMessage {
     object_id: String,
     collab_messages: Vec<ClientCollabMessage>,
-    ret: tokio::sync::oneshot::Sender<Result<(), RealtimeError>>,
+    /// true if the group applied all messages, or there is no group to apply them
+    ret: tokio::sync::oneshot::Sender<Result<bool, RealtimeError>>,
   },
 }
 
+/// How long a group is given to apply the messages of a [CollaborationCommand::ServerSendCollabMessage]
+pub const SERVER_MESSAGE_ACK_TIMEOUT: Duration = Duration::from_secs(5);
+
 const BATCH_GET_ENCODE_COLLAB_CONCURRENCY: usize = 10;
 
 pub(crate) fn spawn_collaboration_command<S>(
@@ -113,6 +118,9 @@ pub(crate) fn spawn_collaboration_command<S>(
             {
               tracing::error!("Send group command error: {}", err);
             };
+          } else {
+            // no one is editing the object, nothing to apply
+            let _ = ret.send(Ok(true));
           }
         },
       }
@@ -1,19 +1,20 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 use async_stream::stream;
 use collab::core::origin::CollabOrigin;
 use collab::entity::EncodedCollab;
 use dashmap::DashMap;
-use futures_util::StreamExt;
+use futures_util::{SinkExt, StreamExt};
 use tracing::{instrument, trace, warn};
 
 use collab_rt_entity::user::RealtimeUser;
 use collab_rt_entity::{AckCode, ClientCollabMessage, ServerCollabMessage, SinkMessage};
-use collab_rt_entity::{CollabAck, RealtimeMessage};
+use collab_rt_entity::{CollabAck, CollabMessage, MsgId, RealtimeMessage};
 use database::collab::CollabStorage;
 
 use crate::client::client_msg_router::ClientMessageRouter;
+use crate::command::SERVER_MESSAGE_ACK_TIMEOUT;
 use crate::error::RealtimeError;
 use crate::group::manager::GroupManager;
 
@@ -35,13 +36,61 @@ pub enum GroupCommand {
   HandleServerCollabMessage {
     object_id: String,
     collab_messages: Vec<ClientCollabMessage>,
-    ret: tokio::sync::oneshot::Sender<Result<(), RealtimeError>>,
+    /// true if the group applied all messages, or there is no group to apply them
+    ret: tokio::sync::oneshot::Sender<Result<bool, RealtimeError>>,
   },
 }
 
 pub type GroupCommandSender = tokio::sync::mpsc::Sender<GroupCommand>;
 pub type GroupCommandReceiver = tokio::sync::mpsc::Receiver<GroupCommand>;
 
+/// Messages sent from the server to a group, waiting for the group to ack them
+struct PendingServerMessages {
+  object_id: String,
+  msg_ids: HashSet<MsgId>,
+  /// acks of the server subscriber, along with the updates broadcast by other subscribers
+  ack_receiver: futures::channel::mpsc::Receiver<CollabMessage>,
+}
+
+impl PendingServerMessages {
+  /// Returns true once the group acked every message as applied. Returns false if a message
+  /// could not be applied, or if the acks did not arrive within [SERVER_MESSAGE_ACK_TIMEOUT].
+  async fn wait_for_acks(mut self) -> bool {
+    let object_id = self.object_id.clone();
+    let wait_for_acks = async move {
+      while !self.msg_ids.is_empty() {
+        let ack = match self.ack_receiver.next().await {
+          Some(CollabMessage::ClientAck(ack)) => ack,
+          Some(_) => continue,
+          None => return false,
+        };
+        if !self.msg_ids.remove(&ack.msg_id) {
+          continue;
+        }
+        if ack.get_code() != AckCode::Success {
+          warn!(
+            "group cannot apply server message, object_id: {}, code: {:?}",
+            self.object_id,
+            ack.get_code()
+          );
+          return false;
+        }
+      }
+      true
+    };
+    match tokio::time::timeout(SERVER_MESSAGE_ACK_TIMEOUT, wait_for_acks).await {
+      Ok(applied) => applied,
+      Err(_) => {
+        warn!(
+          "timeout waiting for the group to apply server message, object_id: {}",
+          object_id
+        );
+        false
+      },
+    }
+  }
+}
+
 /// Each group has a command runner to handle the group command. GroupCommandRunner is designed to run
 /// in tokio multi-thread runtime. It will receive the group command from the receiver and handle the
 /// command.
@@ -98,11 +147,24 @@ where
             collab_messages,
             ret,
           } => {
-            let res = self
+            match self
               .handle_server_collab_messages(object_id, collab_messages)
-              .await;
-            if let Err(err) = ret.send(res) {
-              warn!("Send handle server collab message result fail: {:?}", err);
+              .await
+            {
+              // the group applies the messages on its own task, waiting for its acks here
+              // would hold back the other commands of the group
+              Ok(Some(pending)) => {
+                tokio::spawn(async move {
+                  if let Err(err) = ret.send(Ok(pending.wait_for_acks().await)) {
+                    warn!("Send handle server collab message result fail: {:?}", err);
+                  }
+                });
+              },
+              res => {
+                if let Err(err) = ret.send(res.map(|_| true)) {
+                  warn!("Send handle server collab message result fail: {:?}", err);
+                }
+              },
             }
           },
         }
@@ -187,15 +249,16 @@ where
   }
 
   /// similar to `handle_client_collab_message`, but the messages are sent from the server instead.
+  /// Returns the messages waiting to be applied by the group, or None if there is no group.
   #[instrument(level = "trace", skip_all)]
   async fn handle_server_collab_messages(
     &self,
     object_id: String,
     messages: Vec<ClientCollabMessage>,
-  ) -> Result<(), RealtimeError> {
+  ) -> Result<Option<PendingServerMessages>, RealtimeError> {
     if messages.is_empty() {
       warn!("Unexpected empty collab messages sent from server");
-      return Ok(());
+      return Ok(None);
     }
 
     let server_rt_user = RealtimeUser {
@@ -206,28 +269,36 @@ where
       app_version: "".to_string(),
     };
 
-    if let Some(group) = self.group_manager.get_group(&object_id).await {
-      let (collab_message_sender, _collab_message_receiver) = futures::channel::mpsc::channel(1);
-      let (mut message_by_oid_sender, message_by_oid_receiver) = futures::channel::mpsc::channel(1);
-      group
-        .subscribe(
-          &server_rt_user,
-          CollabOrigin::Server,
-          collab_message_sender,
-          message_by_oid_receiver,
-        )
-        .await;
-      let message = HashMap::from([(object_id.clone(), messages)]);
-      if let Err(err) = message_by_oid_sender.try_send(message) {
-        tracing::error!(
-          "failed to send message to group: {}, object_id: {}",
-          err,
-          object_id
-        );
-      }
+    let group = match self.group_manager.get_group(&object_id).await {
+      Some(group) => group,
+      // no one is editing the object, nothing to apply
+      None => return Ok(None),
     };
-
-    Ok(())
+    // the group acks every message it applied through the subscriber's sink
+    let (collab_message_sender, ack_receiver) = futures::channel::mpsc::channel(1);
+    let (mut message_by_oid_sender, message_by_oid_receiver) = futures::channel::mpsc::channel(1);
+    group
+      .subscribe(
+        &server_rt_user,
+        CollabOrigin::Server,
+        collab_message_sender,
+        message_by_oid_receiver,
+      )
+      .await;
+    let msg_ids = messages.iter().map(|message| message.msg_id()).collect();
+    let message = HashMap::from([(object_id.clone(), messages)]);
+    message_by_oid_sender.send(message).await.map_err(|err| {
+      RealtimeError::Internal(anyhow::anyhow!(
+        "failed to send message to group: {}, object_id: {}",
+        err,
+        object_id
+      ))
+    })?;
+    Ok(Some(PendingServerMessages {
+      object_id,
+      msg_ids,
+      ack_receiver,
+    }))
   }
 
   async fn subscribe_group(
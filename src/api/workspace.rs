@@ -11,6 +11,7 @@ use futures_util::future::try_join_all;
 use prost::Message as ProstMessage;
 use rayon::prelude::*;
 use sqlx::types::uuid;
+use std::sync::Arc;
 use std::time::Instant;
 
 use tokio_stream::StreamExt;
@@ -186,6 +187,10 @@ pub fn workspace_scope() -> Scope {
       web::resource("{workspace_id}/published-duplicate")
         .route(web::post().to(post_published_duplicate_handler)),
     )
+    .service(
+      web::resource("/published-duplicate/{job_id}")
+        .route(web::get().to(get_published_duplicate_status_handler)),
+    )
     .service(
       web::resource("/{workspace_id}/published-info")
         .route(web::get().to(list_published_collab_info_handler)),
@@ -1375,27 +1380,73 @@ async fn post_published_duplicate_handler(
   workspace_id: web::Path<String>,
   state: Data<AppState>,
   params: Json<PublishedDuplicate>,
-) -> Result<Json<AppResponse<()>>> {
+) -> Result<Json<AppResponse<PublishedDuplicateJob>>> {
   let uid = state.user_cache.get_user_uid(&user_uuid).await?;
   state
     .workspace_access_control
     .enforce_action(&uid, &workspace_id.to_string(), Action::Write)
     .await?;
   let params = params.into_inner();
-  biz::workspace::publish_dup::duplicate_published_collab_to_workspace(
-    &state.pg_pool,
-    state.bucket_client.clone(),
-    state.collab_access_control_storage.clone(),
-    uid,
-    params.published_view_id,
-    workspace_id.into_inner(),
-    params.dest_view_id,
-  )
-  .await?;
+  let workspace_id = workspace_id.into_inner();
+  let run_async = params.run_async;
+  let progress = Arc::new(biz::workspace::publish_dup_job::DuplicationProgress::default());
+  let duplication = {
+    let state = state.clone();
+    let workspace_id = workspace_id.clone();
+    let progress = progress.clone();
+    async move {
+      biz::workspace::publish_dup::duplicate_published_collab_to_workspace(
+        &state.pg_pool,
+        Some(state.bucket_client.clone()),
+        state.collab_access_control_storage.clone(),
+        state.collab_access_control_storage.clone(),
+        uid,
+        params.published_view_id,
+        workspace_id,
+        params.dest_view_id,
+        state.config.published_collab.duplicate_row_concurrency,
+        params.strategy,
+        biz::workspace::publish_dup::CollisionPolicy::default(),
+        progress,
+      )
+      .await
+    }
+  };
 
+  if run_async {
+    let job_id = biz::workspace::publish_dup_job::spawn_duplication_job(
+      state.redis_connection_manager.clone(),
+      state.duplication_job_semaphore.clone(),
+      workspace_id,
+      progress,
+      duplication,
+    )
+    .await?;
+    return Ok(Json(AppResponse::Ok().with_data(PublishedDuplicateJob {
+      job_id: job_id.to_string(),
+    })));
+  }
+
+  duplication.await?;
   Ok(Json(AppResponse::Ok()))
 }
 
+async fn get_published_duplicate_status_handler(
+  user_uuid: UserUuid,
+  job_id: web::Path<Uuid>,
+  state: Data<AppState>,
+) -> Result<Json<AppResponse<DuplicationStatus>>> {
+  let uid = state.user_cache.get_user_uid(&user_uuid).await?;
+  let job =
+    biz::workspace::publish_dup_job::get_duplication_job(&state.redis_connection_manager, &job_id)
+      .await?;
+  state
+    .workspace_access_control
+    .enforce_action(&uid, &job.workspace_id, Action::Read)
+    .await?;
+  Ok(Json(AppResponse::Ok().with_data(job.status)))
+}
+
 async fn list_published_collab_info_handler(
   workspace_id: web::Path<Uuid>,
   state: Data<AppState>,
@@ -4,6 +4,7 @@ use std::time::Duration;
 
 use access_control::casbin::access::AccessControl;
 use access_control::casbin::collab::{CollabAccessControlImpl, RealtimeCollabAccessControlImpl};
+use access_control::casbin::notification::spawn_listen_on_policy_change;
 use access_control::casbin::workspace::WorkspaceAccessControlImpl;
 use access_control::collab::{CollabAccessControl, RealtimeAccessControl};
 use access_control::noops::collab::{
@@ -32,7 +33,7 @@ use openssl::ssl::{SslAcceptor, SslAcceptorBuilder, SslFiletype, SslMethod};
 use openssl::x509::X509;
 use secrecy::{ExposeSecret, Secret};
 use sqlx::{postgres::PgPoolOptions, PgPool};
-use tokio::sync::RwLock;
+use tokio::sync::{RwLock, Semaphore};
 use tracing::{error, info, warn};
 
 use appflowy_ai_client::client::AppFlowyAIClient;
@@ -262,6 +263,10 @@ pub async fn init_state(config: &Config, rt_cmd_tx: CLCommandSender) -> Result<A
   );
   let access_control =
     AccessControl::new(pg_pool.clone(), metrics.access_control_metrics.clone()).await?;
+  spawn_listen_on_policy_change(
+    pg_listeners.subscribe_policy_change(),
+    access_control.clone(),
+  );
 
   let user_cache = UserCache::new(pg_pool.clone()).await;
   let collab_access_control: Arc<dyn CollabAccessControl> =
@@ -339,6 +344,9 @@ pub async fn init_state(config: &Config, rt_cmd_tx: CLCommandSender) -> Result<A
     ai_client: appflowy_ai_client,
     grpc_history_client,
     indexer_provider,
+    duplication_job_semaphore: Arc::new(Semaphore::new(
+      config.published_collab.duplicate_job_concurrency,
+    )),
   })
 }
 
@@ -1,4 +1,4 @@
-use access_control::casbin::notification::WorkspaceMemberNotification;
+use access_control::casbin::notification::{PolicyNotification, WorkspaceMemberNotification};
 use anyhow::Error;
 use appflowy_collaborate::collab::notification::CollabMemberNotification;
 use database::listener::PostgresDBListener;
@@ -7,12 +7,21 @@ use sqlx::PgPool;
 
 pub struct PgListeners {
   user_listener: UserListener,
+  policy_listener: PolicyListener,
 }
 
 impl PgListeners {
   pub async fn new(pg_pool: &PgPool) -> Result<Self, Error> {
     let user_listener = UserListener::new(pg_pool, "af_user_channel").await?;
-    Ok(Self { user_listener })
+    let policy_listener = PolicyListener::new(pg_pool, "af_policy_channel").await?;
+    Ok(Self {
+      user_listener,
+      policy_listener,
+    })
+  }
+
+  pub fn subscribe_policy_change(&self) -> tokio::sync::broadcast::Receiver<PolicyNotification> {
+    self.policy_listener.notify.subscribe()
   }
 
   pub fn subscribe_user_change(&self, uid: i64) -> tokio::sync::mpsc::Receiver<AFUserNotification> {
@@ -34,3 +43,4 @@ impl PgListeners {
 pub type CollabMemberListener = PostgresDBListener<CollabMemberNotification>;
 pub type UserListener = PostgresDBListener<AFUserNotification>;
 pub type WorkspaceMemberListener = PostgresDBListener<WorkspaceMemberNotification>;
+pub type PolicyListener = PostgresDBListener<PolicyNotification>;
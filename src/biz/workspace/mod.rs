@@ -2,3 +2,4 @@ pub mod ops;
 pub mod page_view;
 pub mod publish;
 pub mod publish_dup;
+pub mod publish_dup_job;
@@ -11,6 +11,7 @@ use std::ops::DerefMut;
 use std::sync::Arc;
 
 use anyhow::{anyhow, Context};
+use async_trait::async_trait;
 use redis::AsyncCommands;
 use serde_json::json;
 use sqlx::{types::uuid, PgPool};
@@ -713,12 +714,13 @@ pub async fn num_pending_task(uid: i64, pg_pool: &PgPool) -> Result<i64, AppErro
   Ok(count)
 }
 
-/// broadcast updates to collab group if exists
+/// broadcast updates to collab group if exists.
+/// Returns false if the group did not confirm that it applied the update.
 pub async fn broadcast_update(
   collab_storage: &CollabAccessControlStorage,
   oid: &str,
   encoded_update: Vec<u8>,
-) -> Result<(), AppError> {
+) -> Result<bool, AppError> {
   tracing::info!("broadcasting update to group: {}", oid);
   let payload = Message::Sync(SyncMessage::Update(encoded_update)).encode_v1();
   let msg = ClientCollabMessage::ClientUpdateSync {
@@ -732,9 +734,34 @@ pub async fn broadcast_update(
 
   collab_storage
     .broadcast_encode_collab(oid.to_string(), vec![msg])
-    .await?;
+    .await
+}
 
-  Ok(())
+/// Delivers collab updates, that were written outside of the realtime server, to the collab
+/// group of the object so that connected editors receive them.
+#[async_trait]
+pub trait GroupBroadcaster: Send + Sync {
+  /// Returns false if the group did not confirm that it applied the update.
+  async fn broadcast_update(&self, oid: &str, encoded_update: Vec<u8>) -> Result<bool, AppError>;
+}
+
+#[async_trait]
+impl GroupBroadcaster for CollabAccessControlStorage {
+  async fn broadcast_update(&self, oid: &str, encoded_update: Vec<u8>) -> Result<bool, AppError> {
+    broadcast_update(self, oid, encoded_update).await
+  }
+}
+
+/// Broadcaster that drops all updates. Used when there is no realtime server,
+/// e.g. offline tooling and tests.
+pub struct NoOpGroupBroadcaster;
+
+#[async_trait]
+impl GroupBroadcaster for NoOpGroupBroadcaster {
+  async fn broadcast_update(&self, oid: &str, _encoded_update: Vec<u8>) -> Result<bool, AppError> {
+    tracing::trace!("skip broadcasting update to group: {}", oid);
+    Ok(true)
+  }
 }
 
 pub fn collab_from_doc_state(doc_state: Vec<u8>, object_id: &str) -> Result<Collab, AppError> {
@@ -2,11 +2,15 @@ use app_error::AppError;
 use appflowy_collaborate::collab::storage::CollabAccessControlStorage;
 
 use anyhow::anyhow;
+use async_trait::async_trait;
 use bytes::Bytes;
+use collab::core::collab::DataSource;
+use collab::entity::EncodedCollab;
 use collab::preclude::Collab;
 use collab_database::database::gen_row_id;
 use collab_database::database::DatabaseBody;
 use collab_database::entity::FieldType;
+use collab_database::fields::TypeOptionData;
 use collab_database::rows::meta_id_from_row_id;
 use collab_database::rows::DatabaseRowBody;
 use collab_database::rows::RowMetaKey;
@@ -14,22 +18,29 @@ use collab_database::rows::CELL_FIELD_TYPE;
 use collab_database::rows::ROW_CELLS;
 use collab_database::template::entity::CELL_DATA;
 use collab_database::workspace_database::{NoPersistenceDatabaseCollabService, WorkspaceDatabase};
-use collab_document::blocks::DocumentData;
+use collab_document::blocks::{Block, DocumentData};
 use collab_document::document::Document;
 use collab_entity::CollabType;
 use collab_folder::{CollabOrigin, Folder, RepeatedViewIdentifier, View};
+use database::collab::cache::CollabCache;
 use database::collab::GetCollabOrigin;
-use database::collab::{select_workspace_database_oid, CollabStorage};
+use database::collab::{
+  is_collab_exists, select_collab_workspace_id, select_workspace_database_oid,
+};
 use database::file::s3_client_impl::AwsS3BucketClientImpl;
 use database::file::BucketClient;
 use database::file::ResponseBlob;
 use database::publish::select_published_data_for_view_id;
 use database::publish::select_published_metadata_for_view_id;
-use database_entity::dto::CollabParams;
+use database::workspace::select_user_role;
+use database_entity::dto::{CollabParams, QueryCollab};
+use futures::stream::{self, StreamExt, TryStreamExt};
 use shared_entity::dto::publish_dto::{PublishDatabaseData, PublishViewInfo, PublishViewMetaData};
-use shared_entity::dto::workspace_dto::ViewLayout;
-use sqlx::PgPool;
-use std::collections::HashSet;
+use shared_entity::dto::workspace_dto::{DuplicationStrategy, ViewLayout};
+use sqlx::{PgPool, Postgres, Transaction};
+use std::collections::{HashSet, VecDeque};
+use std::future::Future;
+use std::ops::DerefMut;
 use std::time::Duration;
 use std::{collections::HashMap, sync::Arc};
 
@@ -45,42 +56,242 @@ use crate::biz::collab::folder_view::to_folder_view_icon;
 use crate::biz::collab::folder_view::to_folder_view_layout;
 use crate::biz::collab::ops::get_latest_collab_encoded;
 
-use super::ops::broadcast_update;
 use super::ops::collab_from_doc_state;
+use super::ops::GroupBroadcaster;
+use super::publish_dup_job::DuplicationProgress;
 
 #[allow(clippy::too_many_arguments)]
 pub async fn duplicate_published_collab_to_workspace(
   pg_pool: &PgPool,
-  bucket_client: AwsS3BucketClientImpl,
-  collab_storage: Arc<CollabAccessControlStorage>,
+  bucket_client: Option<AwsS3BucketClientImpl>,
+  collab_storage: Arc<dyn DuplicatorCollabStorage>,
+  broadcaster: Arc<dyn GroupBroadcaster>,
   dest_uid: i64,
   publish_view_id: String,
   dest_workspace_id: String,
   dest_view_id: String,
-) -> Result<(), AppError> {
-  let copier = PublishCollabDuplicator::new(
-    pg_pool.clone(),
-    bucket_client,
-    collab_storage.clone(),
-    dest_uid,
-    dest_workspace_id,
-    dest_view_id,
-  );
-
+  row_duplicate_concurrency: usize,
+  strategy: DuplicationStrategy,
+  collision_policy: CollisionPolicy,
+  progress: Arc<DuplicationProgress>,
+) -> Result<String, AppError> {
   let time_now = chrono::Utc::now().timestamp_millis();
-  copier.duplicate(&publish_view_id).await?;
+  let publish_view_id = publish_view_id.as_str();
+  let root_view_id =
+    retry_on_transaction_conflict(DUPLICATION_MAX_ATTEMPTS, DUPLICATION_RETRY_BACKOFF, || {
+      // every attempt starts over with a new duplicator, which generates new ids
+      let copier = PublishCollabDuplicator::new(
+        pg_pool.clone(),
+        bucket_client.clone(),
+        collab_storage.clone(),
+        broadcaster.clone(),
+        dest_uid,
+        dest_workspace_id.clone(),
+        dest_view_id.clone(),
+        row_duplicate_concurrency,
+        strategy,
+        collision_policy,
+        progress.clone(),
+      );
+      copier.duplicate(publish_view_id)
+    })
+    .await?;
   let elapsed = chrono::Utc::now().timestamp_millis() - time_now;
   tracing::info!(
     "duplicate_published_collab_to_workspace: elapsed time: {}ms",
     elapsed
   );
-  Ok(())
+  Ok(root_view_id)
+}
+
+const DUPLICATION_MAX_ATTEMPTS: usize = 3;
+const DUPLICATION_RETRY_BACKOFF: Duration = Duration::from_millis(100);
+const BROADCAST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Broadcasts an update of a committed collab to its group. Failures are only logged, connected
+/// editors see the update after reloading the collab.
+async fn broadcast_committed_update(
+  broadcaster: &dyn GroupBroadcaster,
+  oid: &str,
+  encoded_update: Vec<u8>,
+) {
+  match tokio::time::timeout(
+    BROADCAST_TIMEOUT,
+    broadcaster.broadcast_update(oid, encoded_update),
+  )
+  .await
+  {
+    Ok(Ok(true)) => {},
+    Ok(Ok(false)) => tracing::warn!(
+      "update is not applied by its group: {}, connected editors might need to reload",
+      oid
+    ),
+    Ok(Err(err)) => error!("Failed to broadcast update of {}: {}", oid, err),
+    Err(_) => tracing::warn!(
+      "Timeout waiting for broadcasting update: {}, connected editors might need to reload",
+      oid
+    ),
+  }
+}
+
+/// Runs `attempt` up to `max_attempts` times, as long as it fails on a transaction conflict
+/// (serialization failure or deadlock). The backoff doubles after every failed attempt.
+/// Other errors are returned immediately. `attempt` must not leave anything behind on failure,
+/// which holds for a transaction that is rolled back.
+pub async fn retry_on_transaction_conflict<T, F, Fut>(
+  max_attempts: usize,
+  initial_backoff: Duration,
+  mut attempt: F,
+) -> Result<T, AppError>
+where
+  F: FnMut() -> Fut,
+  Fut: Future<Output = Result<T, AppError>>,
+{
+  let mut backoff = initial_backoff;
+  let mut attempt_count = 1;
+  loop {
+    match attempt().await {
+      Err(err) if err.is_transaction_conflict() && attempt_count < max_attempts => {
+        tracing::warn!(
+          "transaction conflict on attempt {}/{}, retrying in {:?}: {}",
+          attempt_count,
+          max_attempts,
+          backoff,
+          err
+        );
+        tokio::time::sleep(backoff).await;
+        backoff *= 2;
+        attempt_count += 1;
+      },
+      result => return result,
+    }
+  }
+}
+
+/// Collab storage calls made by [PublishCollabDuplicator] on the destination workspace.
+/// Permissions are checked by the caller of the duplication, not by the storage.
+#[async_trait]
+pub trait DuplicatorCollabStorage: Send + Sync {
+  /// Returns the latest state of collab `oid`, as seen by user `uid`
+  async fn get_latest_collab(
+    &self,
+    uid: i64,
+    workspace_id: &str,
+    oid: &str,
+    collab_type: CollabType,
+  ) -> Result<EncodedCollab, AppError>;
+
+  /// Writes a collab within `txn`, whether it exists already or not. Nothing outside of `txn`
+  /// may be changed: the duplication is retried from scratch after rolling back `txn`.
+  async fn insert_collab_with_transaction(
+    &self,
+    workspace_id: &str,
+    uid: &i64,
+    params: &CollabParams,
+    txn: &mut Transaction<'_, Postgres>,
+    action_description: &str,
+  ) -> Result<(), AppError>;
+
+  /// Applies what goes along with a collab written by [Self::insert_collab_with_transaction]
+  /// outside of the database, e.g. access policies and caches, once `txn` is committed
+  async fn finish_committed_collab(&self, uid: &i64, params: &CollabParams)
+    -> Result<(), AppError>;
+}
+
+#[async_trait]
+impl DuplicatorCollabStorage for CollabAccessControlStorage {
+  async fn get_latest_collab(
+    &self,
+    uid: i64,
+    workspace_id: &str,
+    oid: &str,
+    collab_type: CollabType,
+  ) -> Result<EncodedCollab, AppError> {
+    get_latest_collab_encoded(
+      self,
+      GetCollabOrigin::User { uid },
+      workspace_id,
+      oid,
+      collab_type,
+    )
+    .await
+  }
+
+  async fn insert_collab_with_transaction(
+    &self,
+    workspace_id: &str,
+    uid: &i64,
+    params: &CollabParams,
+    txn: &mut Transaction<'_, Postgres>,
+    action_description: &str,
+  ) -> Result<(), AppError> {
+    self
+      .insert_new_collab_to_disk_with_transaction(
+        workspace_id,
+        uid,
+        params,
+        txn,
+        action_description,
+      )
+      .await
+  }
+
+  async fn finish_committed_collab(
+    &self,
+    uid: &i64,
+    params: &CollabParams,
+  ) -> Result<(), AppError> {
+    self.finish_committed_new_collab(uid, params).await
+  }
+}
+
+/// Reads and writes collabs through the cache only, without the realtime server and without
+/// access control. Used when there is no realtime server, e.g. offline tooling and tests.
+#[async_trait]
+impl DuplicatorCollabStorage for CollabCache {
+  async fn get_latest_collab(
+    &self,
+    _uid: i64,
+    _workspace_id: &str,
+    oid: &str,
+    collab_type: CollabType,
+  ) -> Result<EncodedCollab, AppError> {
+    self
+      .get_encode_collab(QueryCollab {
+        object_id: oid.to_string(),
+        collab_type,
+      })
+      .await
+  }
+
+  async fn insert_collab_with_transaction(
+    &self,
+    workspace_id: &str,
+    uid: &i64,
+    params: &CollabParams,
+    txn: &mut Transaction<'_, Postgres>,
+    _action_description: &str,
+  ) -> Result<(), AppError> {
+    self
+      .insert_encode_collab_to_disk(workspace_id, uid, params.clone(), txn)
+      .await
+  }
+
+  async fn finish_committed_collab(
+    &self,
+    _uid: &i64,
+    params: &CollabParams,
+  ) -> Result<(), AppError> {
+    self.insert_encode_collab_to_mem(params).await
+  }
 }
 
 pub struct PublishCollabDuplicator {
   /// for fetching and writing folder data
   /// of dest workspace
-  collab_storage: Arc<CollabAccessControlStorage>,
+  collab_storage: Arc<dyn DuplicatorCollabStorage>,
+  /// for notifying connected editors about the updated folder and workspace database
+  broadcaster: Arc<dyn GroupBroadcaster>,
   /// A map to store the old view_id that was duplicated and new view_id assigned.
   /// If value is none, it means the view_id is not published.
   duplicated_refs: HashMap<String, Option<String>>,
@@ -102,24 +313,51 @@ pub struct PublishCollabDuplicator {
   /// for fetching published data
   /// and writing them to dest workspace
   pg_pool: PgPool,
-  /// for fetching published data from s3
-  bucket_client: AwsS3BucketClientImpl,
+  /// for fetching published data from s3, published data is read from postgres if None
+  bucket_client: Option<AwsS3BucketClientImpl>,
   /// user initiating the duplication
   duplicator_uid: i64,
   /// workspace to duplicate into
   dest_workspace_id: String,
   /// view of workspace to duplicate into
   dest_view_id: String,
+  /// max number of database rows decoded and encoded in parallel
+  row_duplicate_concurrency: usize,
+  /// whether databases embedded in documents are copied or linked
+  strategy: DuplicationStrategy,
+  /// what to do when a duplicated object id already exists in the destination
+  collision_policy: CollisionPolicy,
+  /// collabs written so far, polled by the duplication job
+  progress: Arc<DuplicationProgress>,
+}
+
+/// Decides what happens when the object id of a duplicated collab already exists in the
+/// destination. Duplication generates fresh ids, collisions happen on imports and retries.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum CollisionPolicy {
+  /// Fail the duplication with [AppError::RecordAlreadyExists]
+  Error,
+  /// Keep the existing collab
+  Skip,
+  /// Replace the existing collab
+  #[default]
+  Overwrite,
 }
 
 impl PublishCollabDuplicator {
+  #[allow(clippy::too_many_arguments)]
   pub fn new(
     pg_pool: PgPool,
-    bucket_client: AwsS3BucketClientImpl,
-    collab_storage: Arc<CollabAccessControlStorage>,
+    bucket_client: Option<AwsS3BucketClientImpl>,
+    collab_storage: Arc<dyn DuplicatorCollabStorage>,
+    broadcaster: Arc<dyn GroupBroadcaster>,
     dest_uid: i64,
     dest_workspace_id: String,
     dest_view_id: String,
+    row_duplicate_concurrency: usize,
+    strategy: DuplicationStrategy,
+    collision_policy: CollisionPolicy,
+    progress: Arc<DuplicationProgress>,
   ) -> Self {
     let ts_now = chrono::Utc::now().timestamp();
     Self {
@@ -134,33 +372,37 @@ impl PublishCollabDuplicator {
       pg_pool,
       bucket_client,
       collab_storage,
+      broadcaster,
       duplicator_uid: dest_uid,
       dest_workspace_id,
       dest_view_id,
+      row_duplicate_concurrency,
+      strategy,
+      collision_policy,
+      progress,
     }
   }
 
-  async fn duplicate(mut self, publish_view_id: &str) -> Result<(), AppError> {
+  /// Returns the id of the duplicated root view
+  async fn duplicate(mut self, publish_view_id: &str) -> Result<String, AppError> {
     // new view after deep copy
     // this is the root of the document/database duplicated
     let mut root_view = match self.deep_copy(gen_view_id(), publish_view_id).await? {
       Some(v) => v,
-      None => {
-        return Err(AppError::RecordNotFound(
-          "view not found, it might be unpublished".to_string(),
-        ))
-      },
+      None => return Err(root_view_not_published_error(&self.pg_pool, publish_view_id).await),
     };
     root_view.parent_view_id.clone_from(&self.dest_view_id);
+    let root_view_id = root_view.id.clone();
 
     // destructuring self to own inner values, avoids cloning
     let PublishCollabDuplicator {
       collab_storage,
+      broadcaster,
       duplicated_refs: _,
       duplicated_db_main_view: _,
       duplicated_db_view: _,
       duplicated_db_row: _,
-      mut views_to_add,
+      views_to_add,
       workspace_databases,
       collabs_to_insert,
       ts_now: _,
@@ -168,12 +410,22 @@ impl PublishCollabDuplicator {
       bucket_client: _,
       duplicator_uid,
       dest_workspace_id,
-      dest_view_id,
+      dest_view_id: _,
+      row_duplicate_concurrency: _,
+      strategy: _,
+      collision_policy,
+      progress,
     } = self;
 
+    // checked before anything is written to the destination workspace
+    reconcile_workspace_databases(&collabs_to_insert, &workspace_databases)?;
+
     // insert all collab object accumulated
     // for self.collabs_to_insert
     let mut txn = pg_pool.begin().await?;
+    // collabs written in `txn`, to be finished once it is committed
+    let mut inserted_collabs = Vec::with_capacity(collabs_to_insert.len() + 2);
+    progress.start_writing(collabs_to_insert.len());
     for (oid, (collab_type, encoded_collab)) in collabs_to_insert.into_iter() {
       let params = CollabParams {
         object_id: oid.clone(),
@@ -181,38 +433,40 @@ impl PublishCollabDuplicator {
         collab_type,
         embeddings: None,
       };
-      let action = format!("duplicate collab: {}", params);
-      collab_storage
-        .insert_new_collab_with_transaction(
-          &dest_workspace_id,
-          &duplicator_uid,
-          params,
-          &mut txn,
-          &action,
-        )
-        .await?;
+      let inserted = insert_collab_for_duplicator(
+        collab_storage.as_ref(),
+        &dest_workspace_id,
+        &duplicator_uid,
+        &params,
+        &mut txn,
+        collision_policy,
+      )
+      .await?;
+      if inserted {
+        inserted_collabs.push(params);
+      }
+      progress.collab_written();
     }
 
     // update database if any
-    if !workspace_databases.is_empty() {
+    let ws_db_update = if !workspace_databases.is_empty() {
       let ws_db_oid = select_workspace_database_oid(&pg_pool, &dest_workspace_id.parse()?).await?;
       let ws_db_collab = {
-        let ws_database_ec = get_latest_collab_encoded(
-          &collab_storage,
-          GetCollabOrigin::User {
-            uid: duplicator_uid,
-          },
-          &dest_workspace_id,
-          &ws_db_oid,
-          CollabType::WorkspaceDatabase,
-        )
-        .await?;
+        let ws_database_ec = collab_storage
+          .get_latest_collab(
+            duplicator_uid,
+            &dest_workspace_id,
+            &ws_db_oid,
+            CollabType::WorkspaceDatabase,
+          )
+          .await?;
         collab_from_doc_state(ws_database_ec.doc_state.to_vec(), &ws_db_oid)?
       };
 
-      let mut ws_db = WorkspaceDatabase::open(ws_db_collab).map_err(|err| {
-        AppError::Unhandled(format!("failed to open workspace database: {}", err))
-      })?;
+      let mut ws_db = WorkspaceDatabase::open(ws_db_collab).map_err(collab_decode_failed(
+        &ws_db_oid,
+        CollabType::WorkspaceDatabase,
+      ))?;
       let (ws_db_updates, updated_ws_w_db_collab) = tokio::task::spawn_blocking(move || {
         let ws_db_updates = {
           let view_ids_by_database_id = workspace_databases
@@ -238,33 +492,35 @@ impl PublishCollabDuplicator {
 
       let updated_ws_w_db_collab = updated_ws_w_db_collab?;
 
+      let params = CollabParams {
+        object_id: ws_db_oid.clone(),
+        encoded_collab_v1: Bytes::from(updated_ws_w_db_collab),
+        collab_type: CollabType::WorkspaceDatabase,
+        embeddings: None,
+      };
       collab_storage
-        .insert_new_collab_with_transaction(
+        .insert_collab_with_transaction(
           &dest_workspace_id,
           &duplicator_uid,
-          CollabParams {
-            object_id: ws_db_oid.clone(),
-            encoded_collab_v1: Bytes::from(updated_ws_w_db_collab),
-            collab_type: CollabType::WorkspaceDatabase,
-            embeddings: None,
-          },
+          &params,
           &mut txn,
           "duplicate workspace database collab",
         )
         .await?;
-      broadcast_update(&collab_storage, &ws_db_oid, ws_db_updates).await?;
-    }
+      inserted_collabs.push(params);
+      Some((ws_db_oid, ws_db_updates))
+    } else {
+      None
+    };
 
-    let collab_folder_encoded = get_latest_collab_encoded(
-      &collab_storage,
-      GetCollabOrigin::User {
-        uid: duplicator_uid,
-      },
-      &dest_workspace_id,
-      &dest_workspace_id,
-      CollabType::Folder,
-    )
-    .await?;
+    let collab_folder_encoded = collab_storage
+      .get_latest_collab(
+        duplicator_uid,
+        &dest_workspace_id,
+        &dest_workspace_id,
+        CollabType::Folder,
+      )
+      .await?;
 
     let cloned_dest_workspace_id = dest_workspace_id.clone();
     let mut folder = tokio::task::spawn_blocking(move || {
@@ -275,54 +531,15 @@ impl PublishCollabDuplicator {
         &cloned_dest_workspace_id,
         vec![],
       )
-      .map_err(|e| AppError::Unhandled(e.to_string()))
+      .map_err(collab_decode_failed(
+        &cloned_dest_workspace_id,
+        CollabType::Folder,
+      ))
     })
     .await??;
 
     let (encoded_update, updated_encoded_collab) = tokio::task::spawn_blocking(move || {
-      let encoded_update = {
-        let mut folder_txn = folder.collab.transact_mut();
-
-        let mut duplicated_view_ids = HashSet::new();
-        duplicated_view_ids.insert(root_view.id.clone());
-        duplicated_view_ids.insert(dest_view_id);
-        folder.body.views.insert(&mut folder_txn, root_view, None);
-
-        // when child views are added, it must have a parent view that is previously added
-        // TODO: if there are too many child views, consider using topological sort
-        loop {
-          if views_to_add.is_empty() {
-            break;
-          }
-
-          let mut inserted = vec![];
-          for (view_id, view) in views_to_add.iter() {
-            // allow to insert if parent view is already inserted
-            // or if view is standalone (view_id == parent_view_id)
-            if duplicated_view_ids.contains(&view.parent_view_id) || *view_id == view.parent_view_id
-            {
-              folder
-                .body
-                .views
-                .insert(&mut folder_txn, view.clone(), None);
-              duplicated_view_ids.insert(view_id.clone());
-              inserted.push(view_id.clone());
-            }
-          }
-          if inserted.is_empty() {
-            tracing::error!(
-              "views not inserted because parent_id does not exists: {:?}",
-              views_to_add.keys()
-            );
-            break;
-          }
-          for view_id in inserted {
-            views_to_add.remove(&view_id);
-          }
-        }
-
-        folder_txn.encode_update_v1()
-      };
+      let encoded_update = insert_views_to_folder(&mut folder, root_view, views_to_add);
 
       // update folder collab
       let updated_encoded_collab = collab_to_bin(folder.collab, CollabType::Folder);
@@ -330,20 +547,22 @@ impl PublishCollabDuplicator {
     })
     .await?;
 
+    let params = CollabParams {
+      object_id: dest_workspace_id.clone(),
+      encoded_collab_v1: updated_encoded_collab.await?.into(),
+      collab_type: CollabType::Folder,
+      embeddings: None,
+    };
     collab_storage
-      .insert_new_collab_with_transaction(
+      .insert_collab_with_transaction(
         &dest_workspace_id,
         &duplicator_uid,
-        CollabParams {
-          object_id: dest_workspace_id.clone(),
-          encoded_collab_v1: updated_encoded_collab.await?.into(),
-          collab_type: CollabType::Folder,
-          embeddings: None,
-        },
+        &params,
         &mut txn,
         "duplicate folder collab",
       )
       .await?;
+    inserted_collabs.push(params);
 
     match tokio::time::timeout(Duration::from_secs(60), txn.commit()).await {
       Ok(result) => result.map_err(AppError::from),
@@ -355,21 +574,28 @@ impl PublishCollabDuplicator {
       },
     }?;
 
-    // broadcast folder changes
-    match tokio::time::timeout(
-      Duration::from_secs(30),
-      broadcast_update(&collab_storage, &dest_workspace_id, encoded_update),
-    )
-    .await
-    {
-      Ok(result) => result.map_err(AppError::from),
-      Err(_) => {
-        error!("Timeout waiting for broadcasting the updates");
-        Err(AppError::RequestTimeout(
-          "timeout while duplicating".to_string(),
-        ))
-      },
+    // the access policies and caches are only updated now, a transaction conflict before the
+    // commit must not leave them behind for collabs that are rolled back
+    for params in inserted_collabs.iter() {
+      if let Err(err) = collab_storage
+        .finish_committed_collab(&duplicator_uid, params)
+        .await
+      {
+        error!(
+          "Failed to finish duplicated collab {}: {}",
+          params.object_id, err
+        );
+      }
+    }
+
+    // broadcast workspace database and folder changes, the duplicated collabs are already
+    // committed at this point, so an unconfirmed broadcast only delays when connected editors
+    // see them
+    if let Some((ws_db_oid, ws_db_updates)) = ws_db_update {
+      broadcast_committed_update(broadcaster.as_ref(), &ws_db_oid, ws_db_updates).await;
     }
+    broadcast_committed_update(broadcaster.as_ref(), &dest_workspace_id, encoded_update).await;
+    Ok(root_view_id)
   }
 
   /// Deep copy a published collab to the destination workspace.
@@ -410,8 +636,10 @@ impl PublishCollabDuplicator {
 
     match metadata.view.layout {
       ViewLayout::Document => {
-        let doc_collab = collab_from_doc_state(published_blob, "")?;
-        let doc = Document::open(doc_collab).map_err(|e| AppError::Unhandled(e.to_string()))?;
+        let doc_collab =
+          decode_published_collab(published_blob, publish_view_id, CollabType::Document)?;
+        let doc = Document::open(doc_collab)
+          .map_err(collab_decode_failed(publish_view_id, CollabType::Document))?;
         let new_doc_view = self
           .deep_copy_doc(publish_view_id, new_view_id, doc, metadata)
           .await?;
@@ -444,7 +672,7 @@ impl PublishCollabDuplicator {
 
     let mut doc_data = doc
       .get_document_data()
-      .map_err(|e| AppError::Unhandled(e.to_string()))?;
+      .map_err(collab_decode_failed(pub_view_id, CollabType::Document))?;
 
     if let Err(err) = self.deep_copy_doc_pages(&mut doc_data, &mut ret_view).await {
       tracing::error!("failed to deep copy doc pages: {}", err);
@@ -454,6 +682,12 @@ impl PublishCollabDuplicator {
       .deep_copy_doc_databases(pub_view_id, &mut doc_data, &mut ret_view)
       .await
     {
+      // linking to a database that the user cannot access must not succeed silently
+      if self.strategy == DuplicationStrategy::ShallowLinkDatabases
+        && err.is_not_enough_permissions()
+      {
+        return Err(err);
+      }
       tracing::error!("failed to deep copy doc databases: {}", err);
     };
 
@@ -462,7 +696,7 @@ impl PublishCollabDuplicator {
       let empty_collab = collab_from_doc_state(vec![], &dup_view_id)?;
       let new_doc = tokio::task::spawn_blocking(move || {
         Document::create_with_data(empty_collab, doc_data)
-          .map_err(|e| AppError::Unhandled(e.to_string()))
+          .map_err(|e| AppError::Internal(anyhow!("failed to create document: {}", e)))
       })
       .await??;
       let new_doc_bin = collab_to_bin(new_doc.split().0, CollabType::Document).await?;
@@ -479,42 +713,28 @@ impl PublishCollabDuplicator {
     ret_view: &mut View,
   ) -> Result<(), AppError> {
     if let Some(text_map) = doc_data.meta.text_map.as_mut() {
-      for (_key, value) in text_map.iter_mut() {
-        let mut js_val = match serde_json::from_str::<serde_json::Value>(value) {
-          Ok(js_val) => js_val,
-          Err(e) => {
-            tracing::error!("failed to parse text_map value({}): {}", value, e);
-            continue;
-          },
-        };
-        let js_array = match js_val.as_array_mut() {
-          Some(js_array) => js_array,
+      for (key, value) in text_map.iter_mut() {
+        let mut js_val = match parse_text_map_value(key, value) {
+          Some(js_val) => js_val,
           None => continue,
         };
 
-        let page_ids = js_array
-          .iter_mut()
-          .flat_map(|js_val| js_val.get_mut("attributes"))
-          .flat_map(|attributes| attributes.get_mut("mention"))
-          .filter(|mention| {
-            mention.get("type").map_or(false, |type_| {
-              type_.as_str().map_or(false, |type_| type_ == "page")
-            })
-          })
-          .flat_map(|mention| mention.get_mut("page_id"));
-
-        for page_id in page_ids {
-          let page_id_str = match page_id.as_str() {
-            Some(page_id_str) => page_id_str,
-            None => continue,
-          };
-          if let Some(new_page_id) = self.deep_copy_view(page_id_str, &ret_view.id).await? {
-            *page_id = serde_json::json!(new_page_id);
+        let mut new_page_ids = HashMap::new();
+        for page_id in collect_page_mention_ids(&js_val) {
+          if new_page_ids.contains_key(&page_id) {
+            continue;
+          }
+          if let Some(new_page_id) = self.deep_copy_view(&page_id, &ret_view.id).await? {
+            new_page_ids.insert(page_id, new_page_id);
           } else {
-            tracing::warn!("deep_copy_doc_pages: view not found: {}", page_id_str);
+            tracing::warn!("deep_copy_doc_pages: view not found: {}", page_id);
           };
         }
+        if new_page_ids.is_empty() {
+          continue;
+        }
 
+        remap_page_mention_ids(&mut js_val, &new_page_ids);
         *value = js_val.to_string();
       }
     }
@@ -567,31 +787,25 @@ impl PublishCollabDuplicator {
     doc_data: &mut DocumentData,
     ret_view: &mut View,
   ) -> Result<(), AppError> {
-    let db_blocks = doc_data
-      .blocks
-      .iter_mut()
-      .filter(|(_, b)| b.ty == "grid" || b.ty == "board" || b.ty == "calendar");
-
-    for (block_id, block) in db_blocks {
+    for (block_id, block) in doc_data.blocks.iter_mut() {
+      let (block_view_id, block_parent_id) = match database_block_ref(block)? {
+        Some(database_block_ref) => database_block_ref,
+        None => continue,
+      };
       tracing::info!("deep_copy_doc_databases: block_id: {}", block_id);
-      let block_view_id = block
-        .data
-        .get("view_id")
-        .ok_or_else(|| AppError::RecordNotFound("view_id not found in block data".to_string()))?
-        .as_str()
-        .ok_or_else(|| AppError::RecordNotFound("view_id not a string".to_string()))?;
-
-      let block_parent_id = block
-        .data
-        .get("parent_id")
-        .ok_or_else(|| AppError::RecordNotFound("view_id not found in block data".to_string()))?
-        .as_str()
-        .ok_or_else(|| AppError::RecordNotFound("view_id not a string".to_string()))?;
+
+      if self.strategy == DuplicationStrategy::ShallowLinkDatabases {
+        // keep the block pointing at the shared database
+        if !self.check_shared_database_access(&block_view_id).await? {
+          tracing::warn!("deep_copy_doc_databases: view not found: {}", block_view_id);
+        }
+        continue;
+      }
 
       if pub_view_id == block_parent_id {
         // inline database in doc
         if let Some(new_view_id) = self
-          .deep_copy_inline_database_in_doc(block_view_id, &ret_view.id)
+          .deep_copy_inline_database_in_doc(&block_view_id, &ret_view.id)
           .await?
         {
           block.data.insert(
@@ -608,7 +822,7 @@ impl PublishCollabDuplicator {
       } else {
         // reference to database
         if let Some((new_view_id, new_parent_id)) = self
-          .deep_copy_ref_database_in_doc(block_view_id, block_parent_id, &ret_view.id)
+          .deep_copy_ref_database_in_doc(&block_view_id, &block_parent_id, &ret_view.id)
           .await?
         {
           block.data.insert(
@@ -628,6 +842,28 @@ impl PublishCollabDuplicator {
     Ok(())
   }
 
+  /// Checks that the database of published database view `pub_db_view_id` can be linked to
+  /// instead of copied: it must belong to the destination workspace and the user initiating
+  /// the duplication must be a member of that workspace.
+  /// Returns false if the view is not published, the block is then left as is, like in a deep
+  /// duplication.
+  async fn check_shared_database_access(&self, pub_db_view_id: &str) -> Result<bool, AppError> {
+    let workspace_id =
+      match select_published_metadata_for_view_id(&self.pg_pool, &pub_db_view_id.parse()?).await? {
+        Some((workspace_id, _)) => workspace_id,
+        None => return Ok(false),
+      };
+    if workspace_id.to_string() != self.dest_workspace_id {
+      return Err(AppError::NotEnoughPermissions);
+    }
+
+    match select_user_role(&self.pg_pool, &self.duplicator_uid, &workspace_id).await {
+      Ok(_) => Ok(true),
+      Err(err) if err.is_record_not_found() => Err(AppError::NotEnoughPermissions),
+      Err(err) => Err(err),
+    }
+  }
+
   /// deep copy inline database for doc
   /// returns new view_id
   /// parent_view_id is assumed to be doc itself
@@ -725,13 +961,17 @@ impl PublishCollabDuplicator {
     new_view_id: String,
   ) -> Result<(String, String, bool), AppError> {
     // collab of database
-    let mut db_collab = collab_from_doc_state(published_db.database_collab.clone(), "")?;
+    let mut db_collab = decode_published_collab(
+      published_db.database_collab.clone(),
+      pub_view_id,
+      CollabType::Database,
+    )?;
     let db_body = DatabaseBody::from_collab(
       &db_collab,
       Arc::new(NoPersistenceDatabaseCollabService),
       None,
     )
-    .ok_or_else(|| AppError::RecordNotFound("no database body found".to_string()))?;
+    .ok_or_else(|| AppError::InvalidPublishPayload("no database body found".to_string()))?;
     let pub_db_id = db_body.get_database_id(&db_collab.context.transact());
 
     // check if the database is already duplicated
@@ -793,170 +1033,75 @@ impl PublishCollabDuplicator {
       let all_fields = db_body.fields.get_all_fields(&txn);
       for mut field in all_fields {
         for (key, type_option_value) in field.type_options.iter_mut() {
-          if *key == FieldType::Relation.type_id() {
-            if let Some(pub_db_id) = type_option_value.get_mut("database_id") {
-              if let Any::String(pub_db_id_str) = pub_db_id {
-                if let Some(pub_rel_db_view) =
-                  published_db.database_relations.get(pub_db_id_str.as_ref())
-                {
-                  if let Some(_dup_view_id) = self
-                    .deep_copy_view(pub_rel_db_view, &self.dest_view_id.to_string())
-                    .await?
-                  {
-                    if let Some(dup_db_id) = self
-                      .duplicated_refs
-                      .get(pub_db_id_str.as_ref())
-                      .cloned()
-                      .flatten()
-                    {
-                      *pub_db_id = Any::String(dup_db_id.into());
-                      db_body.fields.update_field(&mut txn, &field.id, |f| {
-                        f.set_type_option(
-                          FieldType::Relation.into(),
-                          Some(type_option_value.clone()),
-                        );
-                      });
-                    };
-                  };
-                };
-              }
+          let (pub_rel_db_id, pub_rel_db_view) =
+            match related_database_view_id(published_db, key, type_option_value) {
+              Some(related_database) => related_database,
+              None => continue,
             };
-          }
-        }
-      }
-    }
-
-    // duplicate db collab rows
-    for (pub_row_id, row_bin_data) in &published_db.database_row_collabs {
-      let dup_row_id = self
-        .duplicated_db_row
-        .get(pub_row_id)
-        .ok_or_else(|| AppError::RecordNotFound(format!("row not found: {}", pub_row_id)))?
-        .clone();
-
-      let mut db_row_collab = collab_from_doc_state(row_bin_data.clone(), &dup_row_id)?;
-      let mut db_row_body = DatabaseRowBody::open(pub_row_id.clone().into(), &mut db_row_collab)
-        .map_err(|e| AppError::Unhandled(e.to_string()))?;
-
-      {
-        let mut txn = db_row_collab.context.transact_mut();
-        // update database_id
-        db_row_body.update(&mut txn, |u| {
-          u.set_database_id(new_db_id.clone());
-        });
-
-        // get row document id before the id update
-        let pub_row_doc_id = db_row_body
-          .document_id(&txn)
-          .map_err(|e| AppError::Unhandled(e.to_string()))?;
-
-        // updates row id along with meta keys
-        db_row_body
-          .update_id(&mut txn, dup_row_id.clone().into())
-          .map_err(|e| AppError::Unhandled(format!("failed to update row id: {:?}", e)))?;
-
-        // duplicate row document if exists
-        if let Some(pub_row_doc_id) = pub_row_doc_id {
-          if let Some(row_doc_doc_state) = published_db
-            .database_row_document_collabs
-            .get(&pub_row_doc_id)
+          if let Some(_dup_view_id) = self
+            .deep_copy_view(pub_rel_db_view, &self.dest_view_id.to_string())
+            .await?
           {
-            match collab_from_doc_state(row_doc_doc_state.to_vec(), &pub_row_doc_id) {
-              Ok(pub_doc_collab) => {
-                let pub_doc =
-                  Document::open(pub_doc_collab).map_err(|e| AppError::Unhandled(e.to_string()))?;
-                let dup_row_doc_id =
-                  meta_id_from_row_id(&dup_row_id.parse()?, RowMetaKey::DocumentId);
-                let mut new_doc_view = Box::pin(self.deep_copy_doc(
-                  &pub_row_doc_id,
-                  dup_row_doc_id.clone(),
-                  pub_doc,
-                  PublishViewMetaData::default(),
-                ))
-                .await?;
-                new_doc_view.parent_view_id.clone_from(&dup_row_doc_id); // orphan folder view
-                self
-                  .views_to_add
-                  .insert(dup_row_doc_id.clone(), new_doc_view);
-              },
-              Err(err) => tracing::error!("failed to open row document: {}", err),
+            if let Some(dup_db_id) = self.duplicated_refs.get(&pub_rel_db_id).cloned().flatten() {
+              type_option_value.insert("database_id".to_string(), Any::String(dup_db_id.into()));
+              db_body.fields.update_field(&mut txn, &field.id, |f| {
+                f.set_type_option(FieldType::Relation.into(), Some(type_option_value.clone()));
+              });
             };
-          } else {
-            tracing::error!("no document found for row: {}", pub_row_doc_id);
           };
         }
+      }
+    }
 
+    // duplicate db collab rows
+    // decoding, rewriting and encoding the rows is CPU bound, so it is done in parallel
+    let dup_rows = duplicate_database_rows(
+      published_db.database_row_collabs.clone(),
+      Arc::new(self.duplicated_db_row.clone()),
+      new_db_id.clone(),
+      self.row_duplicate_concurrency,
+    )
+    .await?;
+    for dup_row in dup_rows {
+      // duplicate row document if exists
+      if let Some(pub_row_doc_id) = dup_row.pub_row_doc_id {
+        if let Some(row_doc_doc_state) = published_db
+          .database_row_document_collabs
+          .get(&pub_row_doc_id)
         {
-          // "cells": Object {
-          //     "MBaTsr": Object {
-          //         "data": Array [
-          //             String("eefb5700-8cf7-411e-9596-f60b9a51916e"),
-          //             String("23d5e054-42c8-4754-ad69-527e4ffc1e46"),
-          //             // above are published row ids of related database
-          //             // we need to replace them with respective duplicated row ids
-          //         ],
-          //         "field_type": Number(10),
-          //         // use this condition to filter out relation cells
-          //     },
-          // },
-          let cells: MapRef = db_row_body
-            .get_data()
-            .get(&txn, ROW_CELLS)
-            .ok_or_else(|| {
-              AppError::RecordNotFound("no cells found in database row collab".to_string())
-            })?
-            .cast()
-            .map_err(|e| AppError::Unhandled(format!("not a map: {:?}", e)))?;
-
-          // collect all cell with field type as relation
-          let mut rel_row_idss = vec![];
-          for (_, out) in cells.iter(&txn) {
-            if let Ok(m) = out.cast::<MapRef>() {
-              if let Some(Out::Any(Any::BigInt(n))) = m.get(&txn, CELL_FIELD_TYPE) {
-                if n == FieldType::Relation as i64 {
-                  match m.get(&txn, CELL_DATA) {
-                    Some(relation_data) => {
-                      if let Ok(arr) = relation_data.cast::<ArrayRef>() {
-                        rel_row_idss.push(arr)
-                      };
-                    },
-                    None => {
-                      tracing::warn!("no data found in relation cell, pub_row_id: {}", pub_row_id)
-                    },
-                  }
-                }
-              }
-            }
-          }
-          // replace all relation cells with duplicated row ids
-          for rel_row_ids in rel_row_idss {
-            let num_refs = rel_row_ids.len(&txn);
-            let mut pub_row_ids = Vec::with_capacity(num_refs as usize);
-            for rel_row_id in rel_row_ids.iter(&txn) {
-              if let Out::Any(Any::String(s)) = rel_row_id {
-                pub_row_ids.push(s);
-              }
-            }
-            rel_row_ids.remove_range(&mut txn, 0, num_refs);
-            for pub_row_id in pub_row_ids {
-              let dup_row_id =
-                self
-                  .duplicated_db_row
-                  .get(pub_row_id.as_ref())
-                  .ok_or_else(|| {
-                    AppError::RecordNotFound(format!("row not found: {}", pub_row_id))
-                  })?;
-              let _ = rel_row_ids.push_back(&mut txn, dup_row_id.as_str());
-            }
-          }
-        }
+          match decode_published_collab(
+            row_doc_doc_state.to_vec(),
+            &pub_row_doc_id,
+            CollabType::Document,
+          ) {
+            Ok(pub_doc_collab) => {
+              let pub_doc = Document::open(pub_doc_collab)
+                .map_err(collab_decode_failed(&pub_row_doc_id, CollabType::Document))?;
+              let dup_row_doc_id =
+                meta_id_from_row_id(&dup_row.dup_row_id.parse()?, RowMetaKey::DocumentId);
+              let mut new_doc_view = Box::pin(self.deep_copy_doc(
+                &pub_row_doc_id,
+                dup_row_doc_id.clone(),
+                pub_doc,
+                PublishViewMetaData::default(),
+              ))
+              .await?;
+              new_doc_view.parent_view_id.clone_from(&dup_row_doc_id); // orphan folder view
+              self
+                .views_to_add
+                .insert(dup_row_doc_id.clone(), new_doc_view);
+            },
+            Err(err) => tracing::error!("failed to open row document: {}", err),
+          };
+        } else {
+          tracing::error!("no document found for row: {}", pub_row_doc_id);
+        };
       }
 
       // write new row collab to storage
-      let db_row_ec_bytes = collab_to_bin(db_row_collab, CollabType::DatabaseRow).await?;
       self.collabs_to_insert.insert(
-        dup_row_id.to_string(),
-        (CollabType::DatabaseRow, db_row_ec_bytes),
+        dup_row.dup_row_id,
+        (CollabType::DatabaseRow, dup_row.encoded_collab),
       );
     }
 
@@ -968,7 +1113,7 @@ impl PublishCollabDuplicator {
       let mut db_views = db_body.views.get_all_views(&txn);
       for db_view in db_views.iter_mut() {
         let new_db_view_id = self.duplicated_db_view.get(&db_view.id).ok_or_else(|| {
-          AppError::Unhandled(format!(
+          AppError::Internal(anyhow!(
             "view not found in duplicated_db_view: {}",
             db_view.id
           ))
@@ -1134,25 +1279,523 @@ impl PublishCollabDuplicator {
     &self,
     view_id: &uuid::Uuid,
   ) -> Result<Option<(PublishViewMetaData, Vec<u8>)>, AppError> {
-    let result = select_published_metadata_for_view_id(&self.pg_pool, view_id).await?;
-    match result {
-      Some((workspace_id, js_val)) => {
-        let metadata = serde_json::from_value(js_val)?;
-        let object_key = format!("published-collab/{}/{}", workspace_id, view_id);
-        match self.bucket_client.get_blob(&object_key).await {
-          Ok(resp) => Ok(Some((metadata, resp.to_blob()))),
-          Err(_) => match select_published_data_for_view_id(&self.pg_pool, view_id).await? {
-            Some((js_val, blob)) => {
-              let metadata = serde_json::from_value(js_val)?;
-              Ok(Some((metadata, blob)))
-            },
-            None => Ok(None),
+    get_published_data_for_view_id(&self.pg_pool, self.bucket_client.as_ref(), view_id).await
+  }
+}
+
+/// Returns the metadata and blob of published view `view_id`, or None if it is not published.
+/// The blob is read from the bucket if a client is given, otherwise (or if the read fails) from
+/// postgres, which keeps a copy of every published blob.
+pub async fn get_published_data_for_view_id(
+  pg_pool: &PgPool,
+  bucket_client: Option<&AwsS3BucketClientImpl>,
+  view_id: &uuid::Uuid,
+) -> Result<Option<(PublishViewMetaData, Vec<u8>)>, AppError> {
+  let bucket_client = match bucket_client {
+    Some(bucket_client) => bucket_client,
+    None => {
+      return match select_published_data_for_view_id(pg_pool, view_id).await? {
+        Some((js_val, blob)) => Ok(Some((serde_json::from_value(js_val)?, blob))),
+        None => Ok(None),
+      }
+    },
+  };
+
+  let result = select_published_metadata_for_view_id(pg_pool, view_id).await?;
+  match result {
+    Some((workspace_id, js_val)) => {
+      let metadata = serde_json::from_value(js_val)?;
+      let object_key = format!("published-collab/{}/{}", workspace_id, view_id);
+      match bucket_client.get_blob(&object_key).await {
+        Ok(resp) => Ok(Some((metadata, resp.to_blob()))),
+        Err(_) => match select_published_data_for_view_id(pg_pool, view_id).await? {
+          Some((js_val, blob)) => {
+            let metadata = serde_json::from_value(js_val)?;
+            Ok(Some((metadata, blob)))
           },
-        }
+          None => Ok(None),
+        },
+      }
+    },
+    None => Ok(None),
+  }
+}
+
+/// A view visited when duplicating a published view
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ReachableView {
+  pub view_id: String,
+  /// None if the view is not published
+  pub name: Option<String>,
+  /// None if the view is not published
+  pub layout: Option<ViewLayout>,
+  pub is_published: bool,
+}
+
+/// Error for a root view without published data. Unpublishing removes the published data, so a
+/// view counts as unpublished when its collab still exists and as not found otherwise. This only
+/// tells the two apart for views whose collab is stored under the view id, i.e. documents: an
+/// unpublished database view is reported as not found, and a document that was never published
+/// is reported as not published.
+async fn root_view_not_published_error(pg_pool: &PgPool, view_id: &str) -> AppError {
+  match is_collab_exists(view_id, pg_pool).await {
+    Ok(true) => AppError::ViewNotPublished {
+      view_id: view_id.to_string(),
+    },
+    Ok(false) => AppError::RecordNotFound(format!("published view not found: {}", view_id)),
+    Err(err) => err.into(),
+  }
+}
+
+/// Lists the views that duplicating `root_publish_view_id` would visit, without copying anything:
+/// the root itself, the pages mentioned in documents, the databases embedded in documents, the
+/// databases related to databases and the views referenced by the documents of database rows.
+/// The references are found the same way as the duplication finds them. Referenced views which
+/// are not published are listed too, since duplication skips them.
+pub async fn collect_reachable_published_views(
+  pg_pool: &PgPool,
+  root_publish_view_id: &str,
+  collab_type: CollabType,
+) -> Result<Vec<ReachableView>, AppError> {
+  if !matches!(collab_type, CollabType::Document | CollabType::Database) {
+    return Err(AppError::InvalidRequest(format!(
+      "collab type not supported: {:?}",
+      collab_type
+    )));
+  }
+
+  let mut reachable_views = vec![];
+  // view ids and database ids, like the keys of the duplicator's `duplicated_refs`: a view is
+  // visited at most once, which also stops at reference cycles, and the references of a database
+  // are followed once, whichever of its views is reached first
+  let mut visited_ids = HashSet::new();
+  let mut view_ids_to_visit = VecDeque::from([root_publish_view_id.to_string()]);
+  while let Some(view_id) = view_ids_to_visit.pop_front() {
+    if !visited_ids.insert(view_id.clone()) {
+      continue;
+    }
+
+    let is_root = view_id == root_publish_view_id;
+    let (metadata, published_blob) =
+      match get_published_data_for_view_id(pg_pool, None, &view_id.parse()?).await? {
+        Some(published_data) => published_data,
+        None if is_root => return Err(root_view_not_published_error(pg_pool, &view_id).await),
+        None => {
+          reachable_views.push(ReachableView {
+            view_id,
+            name: None,
+            layout: None,
+            is_published: false,
+          });
+          continue;
+        },
+      };
+
+    let layout = metadata.view.layout;
+    if is_root && collab_type_of_layout(&layout).as_ref() != Some(&collab_type) {
+      return Err(AppError::InvalidRequest(format!(
+        "view {} is a {:?}, not a {:?}",
+        view_id, layout, collab_type
+      )));
+    }
+    let referenced_view_ids = match layout {
+      ViewLayout::Document => published_doc_data(published_blob, &view_id)
+        .map(|doc_data| referenced_view_ids_in_doc(&doc_data)),
+      ViewLayout::Grid | ViewLayout::Board | ViewLayout::Calendar => {
+        serde_json::from_slice::<PublishDatabaseData>(&published_blob)
+          .map_err(AppError::from)
+          .and_then(|published_db| referenced_view_ids_in_database(&view_id, &published_db))
+          .map(|(database_id, view_ids)| {
+            // duplication links the view to the database duplicated already
+            if visited_ids.insert(database_id) {
+              view_ids
+            } else {
+              vec![]
+            }
+          })
+      },
+      ViewLayout::Chat => Ok(vec![]),
+    };
+    match referenced_view_ids {
+      Ok(view_ids) => view_ids_to_visit.extend(view_ids),
+      // duplication skips the references of pages that cannot be decoded
+      Err(err) if !is_root => {
+        tracing::warn!("failed to collect views referenced by {}: {}", view_id, err);
+      },
+      Err(err) => return Err(err),
+    }
+
+    reachable_views.push(ReachableView {
+      view_id,
+      name: Some(metadata.view.name),
+      layout: Some(layout),
+      is_published: true,
+    });
+  }
+
+  Ok(reachable_views)
+}
+
+fn collab_type_of_layout(layout: &ViewLayout) -> Option<CollabType> {
+  match layout {
+    ViewLayout::Document => Some(CollabType::Document),
+    ViewLayout::Grid | ViewLayout::Board | ViewLayout::Calendar => Some(CollabType::Database),
+    ViewLayout::Chat => None,
+  }
+}
+
+fn published_doc_data(
+  published_blob: Vec<u8>,
+  pub_view_id: &str,
+) -> Result<DocumentData, AppError> {
+  let doc_collab = decode_published_collab(published_blob, pub_view_id, CollabType::Document)?;
+  Document::open(doc_collab)
+    .map_err(collab_decode_failed(pub_view_id, CollabType::Document))?
+    .get_document_data()
+    .map_err(collab_decode_failed(pub_view_id, CollabType::Document))
+}
+
+/// Returns the ids of the pages mentioned in, and the databases embedded in, a published document
+fn referenced_view_ids_in_doc(doc_data: &DocumentData) -> Vec<String> {
+  let mut view_ids = vec![];
+  if let Some(text_map) = doc_data.meta.text_map.as_ref() {
+    for (key, value) in text_map.iter() {
+      if let Some(js_val) = parse_text_map_value(key, value) {
+        view_ids.extend(collect_page_mention_ids(&js_val));
+      }
+    }
+  }
+  for block in doc_data.blocks.values() {
+    match database_block_ref(block) {
+      Ok(Some((view_id, _))) => view_ids.push(view_id),
+      Ok(None) => {},
+      // duplication stops at the first malformed database block
+      Err(err) => {
+        tracing::warn!("skipping malformed database block {}: {}", block.id, err);
+        break;
+      },
+    }
+  }
+  view_ids
+}
+
+/// Returns the id of a published database along with the views of the databases related to it,
+/// and the views referenced by the documents of its rows
+fn referenced_view_ids_in_database(
+  pub_view_id: &str,
+  published_db: &PublishDatabaseData,
+) -> Result<(String, Vec<String>), AppError> {
+  let db_collab = decode_published_collab(
+    published_db.database_collab.clone(),
+    pub_view_id,
+    CollabType::Database,
+  )?;
+  let db_body = DatabaseBody::from_collab(
+    &db_collab,
+    Arc::new(NoPersistenceDatabaseCollabService),
+    None,
+  )
+  .ok_or_else(|| AppError::InvalidPublishPayload("no database body found".to_string()))?;
+
+  let mut view_ids = vec![];
+  for field in db_body.fields.get_all_fields(&db_collab.context.transact()) {
+    for (key, type_option_value) in field.type_options.iter() {
+      if let Some((_, related_view_id)) =
+        related_database_view_id(published_db, key, type_option_value)
+      {
+        view_ids.push(related_view_id.clone());
+      }
+    }
+  }
+  for (row_doc_id, row_doc_state) in published_db.database_row_document_collabs.iter() {
+    match published_doc_data(row_doc_state.clone(), row_doc_id) {
+      Ok(doc_data) => view_ids.extend(referenced_view_ids_in_doc(&doc_data)),
+      // duplication skips row documents that cannot be opened
+      Err(err) => tracing::warn!("failed to open row document {}: {}", row_doc_id, err),
+    }
+  }
+  let database_id = db_body.get_database_id(&db_collab.context.transact());
+  Ok((database_id, view_ids))
+}
+
+/// Returns the (view id, parent id) of the database embedded in `block`, or None if it is not a
+/// database block
+fn database_block_ref(block: &Block) -> Result<Option<(String, String)>, AppError> {
+  if !matches!(block.ty.as_str(), "grid" | "board" | "calendar") {
+    return Ok(None);
+  }
+  let view_id = block
+    .data
+    .get("view_id")
+    .ok_or_else(|| AppError::RecordNotFound("view_id not found in block data".to_string()))?
+    .as_str()
+    .ok_or_else(|| AppError::RecordNotFound("view_id not a string".to_string()))?;
+  let parent_id = block
+    .data
+    .get("parent_id")
+    .ok_or_else(|| AppError::RecordNotFound("parent_id not found in block data".to_string()))?
+    .as_str()
+    .ok_or_else(|| AppError::RecordNotFound("parent_id not a string".to_string()))?;
+  Ok(Some((view_id.to_string(), parent_id.to_string())))
+}
+
+/// Returns the published (database id, view id) of the database that a relation field refers
+/// to, or None if `type_option_value` is not a relation or the related database is not published
+/// along with `published_db`
+fn related_database_view_id<'a>(
+  published_db: &'a PublishDatabaseData,
+  type_option_key: &str,
+  type_option_value: &TypeOptionData,
+) -> Option<(String, &'a String)> {
+  if type_option_key != FieldType::Relation.type_id() {
+    return None;
+  }
+  let related_db_id = match type_option_value.get("database_id")? {
+    Any::String(related_db_id) => related_db_id.to_string(),
+    _ => return None,
+  };
+  let related_view_id = published_db.database_relations.get(&related_db_id)?;
+  Some((related_db_id, related_view_id))
+}
+
+/// Inserts a duplicated collab into the destination workspace, following `collision_policy` if
+/// its object id already exists there. Returns false if the insertion was skipped. The object id
+/// existing in another workspace is always an error, whatever the policy.
+pub async fn insert_collab_for_duplicator(
+  collab_storage: &dyn DuplicatorCollabStorage,
+  workspace_id: &str,
+  uid: &i64,
+  params: &CollabParams,
+  txn: &mut Transaction<'_, Postgres>,
+  collision_policy: CollisionPolicy,
+) -> Result<bool, AppError> {
+  if let Some(existing_workspace_id) =
+    select_collab_workspace_id(&params.object_id, txn.deref_mut()).await?
+  {
+    if existing_workspace_id != uuid::Uuid::parse_str(workspace_id)? {
+      return Err(AppError::RecordAlreadyExists(format!(
+        "collab {} already exists in another workspace: {}",
+        params.object_id, existing_workspace_id
+      )));
+    }
+    match collision_policy {
+      CollisionPolicy::Error => {
+        return Err(AppError::RecordAlreadyExists(format!(
+          "collab already exists in destination: {}",
+          params.object_id
+        )));
+      },
+      CollisionPolicy::Skip => {
+        tracing::info!(
+          "collab already exists in destination, skipping: {}",
+          params.object_id
+        );
+        return Ok(false);
       },
-      None => Ok(None),
+      CollisionPolicy::Overwrite => {},
     }
   }
+
+  let action = format!("duplicate collab: {}", params);
+  collab_storage
+    .insert_collab_with_transaction(workspace_id, uid, params, txn, &action)
+    .await?;
+  Ok(true)
+}
+
+/// Parses a text_map value of a document. Returns None, leaving the value as is, if it's not
+/// valid JSON or is a plain scalar that can't contain any mention.
+pub fn parse_text_map_value(key: &str, value: &str) -> Option<serde_json::Value> {
+  match serde_json::from_str::<serde_json::Value>(value) {
+    Ok(js_val) if js_val.is_array() || js_val.is_object() => Some(js_val),
+    Ok(_) => {
+      tracing::warn!(
+        "text_map value of {} is neither an array nor an object, left as is: {}",
+        key,
+        value
+      );
+      None
+    },
+    Err(err) => {
+      tracing::warn!(
+        "text_map value of {} is not valid json, left as is: {}, value: {}",
+        key,
+        err,
+        value
+      );
+      None
+    },
+  }
+}
+
+/// Returns the page ids of all page mentions in a text_map value, at any nesting level.
+/// A page mention looks like `{"mention": {"type": "page", "page_id": "<view id>"}}`.
+pub fn collect_page_mention_ids(js_val: &serde_json::Value) -> Vec<String> {
+  let mut page_ids = vec![];
+  collect_page_mention_ids_into(js_val, &mut page_ids);
+  page_ids
+}
+
+fn collect_page_mention_ids_into(js_val: &serde_json::Value, page_ids: &mut Vec<String>) {
+  match js_val {
+    serde_json::Value::Array(values) => {
+      for value in values {
+        collect_page_mention_ids_into(value, page_ids);
+      }
+    },
+    serde_json::Value::Object(map) => {
+      if let Some(page_id) = map.get("mention").and_then(page_mention_id) {
+        page_ids.push(page_id.to_string());
+      }
+      for (key, value) in map {
+        if key != "mention" {
+          collect_page_mention_ids_into(value, page_ids);
+        }
+      }
+    },
+    _ => {},
+  }
+}
+
+/// Replaces the page ids of the page mentions in a text_map value with the ids in
+/// `new_page_ids`. Mentions of pages not in `new_page_ids` are left as is.
+pub fn remap_page_mention_ids(
+  js_val: &mut serde_json::Value,
+  new_page_ids: &HashMap<String, String>,
+) {
+  match js_val {
+    serde_json::Value::Array(values) => {
+      for value in values {
+        remap_page_mention_ids(value, new_page_ids);
+      }
+    },
+    serde_json::Value::Object(map) => {
+      for (key, value) in map.iter_mut() {
+        if key != "mention" {
+          remap_page_mention_ids(value, new_page_ids);
+          continue;
+        }
+        let new_page_id = page_mention_id(value).and_then(|page_id| new_page_ids.get(page_id));
+        if let (Some(new_page_id), Some(mention)) = (new_page_id, value.as_object_mut()) {
+          mention.insert("page_id".to_string(), serde_json::json!(new_page_id));
+        }
+      }
+    },
+    _ => {},
+  }
+}
+
+fn page_mention_id(mention: &serde_json::Value) -> Option<&str> {
+  if mention.get("type")?.as_str()? != "page" {
+    return None;
+  }
+  mention.get("page_id")?.as_str()
+}
+
+/// Inserts `root_view` and `views_to_add` into the folder and returns the encoded update.
+/// Views whose id already exists in the folder are skipped, so re-running a duplication
+/// with the same view ids (e.g. an import with fixed ids) does not create duplicate entries.
+pub fn insert_views_to_folder(
+  folder: &mut Folder,
+  root_view: View,
+  mut views_to_add: HashMap<String, View>,
+) -> Vec<u8> {
+  let mut folder_txn = folder.collab.transact_mut();
+
+  let mut duplicated_view_ids = HashSet::new();
+  duplicated_view_ids.insert(root_view.id.clone());
+  duplicated_view_ids.insert(root_view.parent_view_id.clone());
+  if folder
+    .body
+    .views
+    .get_view(&folder_txn, &root_view.id)
+    .is_none()
+  {
+    folder.body.views.insert(&mut folder_txn, root_view, None);
+  } else {
+    tracing::info!("view already exists in folder, skipping: {}", root_view.id);
+  }
+
+  // when child views are added, it must have a parent view that is previously added
+  // TODO: if there are too many child views, consider using topological sort
+  loop {
+    if views_to_add.is_empty() {
+      break;
+    }
+
+    let mut inserted = vec![];
+    for (view_id, view) in views_to_add.iter() {
+      // allow to insert if parent view is already inserted
+      // or if view is standalone (view_id == parent_view_id)
+      if duplicated_view_ids.contains(&view.parent_view_id) || *view_id == view.parent_view_id {
+        if folder.body.views.get_view(&folder_txn, view_id).is_none() {
+          folder
+            .body
+            .views
+            .insert(&mut folder_txn, view.clone(), None);
+        } else {
+          tracing::info!("view already exists in folder, skipping: {}", view_id);
+        }
+        duplicated_view_ids.insert(view_id.clone());
+        inserted.push(view_id.clone());
+      }
+    }
+    if inserted.is_empty() {
+      tracing::error!(
+        "views not inserted because parent_id does not exists: {:?}",
+        views_to_add.keys()
+      );
+      break;
+    }
+    for view_id in inserted {
+      views_to_add.remove(&view_id);
+    }
+  }
+
+  folder_txn.encode_update_v1()
+}
+
+/// Verifies that every duplicated database has a workspace database entry with at least one
+/// linked view, and that every entry refers to a duplicated database. A database without entry
+/// would be orphaned, it cannot be opened from the destination workspace.
+/// Runs before anything is written, so a mismatch aborts the duplication without leaving
+/// orphaned collabs behind. The error lists every mismatched database id.
+fn reconcile_workspace_databases(
+  collabs_to_insert: &HashMap<String, (CollabType, Vec<u8>)>,
+  workspace_databases: &HashMap<String, Vec<String>>,
+) -> Result<(), AppError> {
+  let mut mismatches = vec![];
+  for (oid, (collab_type, _)) in collabs_to_insert {
+    if *collab_type != CollabType::Database {
+      continue;
+    }
+    match workspace_databases.get(oid) {
+      Some(view_ids) if !view_ids.is_empty() => {},
+      _ => mismatches.push(format!("database {} has no linked view", oid)),
+    }
+  }
+  for database_id in workspace_databases.keys() {
+    if !matches!(
+      collabs_to_insert.get(database_id),
+      Some((CollabType::Database, _))
+    ) {
+      mismatches.push(format!(
+        "workspace database entry {} refers to a database that is not duplicated",
+        database_id
+      ));
+    }
+  }
+  if mismatches.is_empty() {
+    return Ok(());
+  }
+
+  mismatches.sort();
+  // the bookkeeping is derived from the published data only, so retrying gives the same result
+  Err(AppError::Internal(anyhow!(
+    "workspace database does not match the duplicated databases, nothing is written: {}. \
+     The published data is inconsistent, retrying the duplication will not fix it",
+    mismatches.join(", ")
+  )))
 }
 
 fn view_info_by_view_id(meta: &PublishViewMetaData) -> HashMap<String, PublishViewInfo> {
@@ -1172,13 +1815,182 @@ fn add_to_view_info(acc: &mut HashMap<String, PublishViewInfo>, view_infos: &[Pu
   }
 }
 
-async fn collab_to_bin(collab: Collab, collab_type: CollabType) -> Result<Vec<u8>, AppError> {
-  tokio::task::spawn_blocking(move || {
-    let bin = collab
-      .encode_collab_v1(|collab| collab_type.validate_require_data(collab))
-      .map_err(|e| AppError::Unhandled(e.to_string()))?
-      .encode_to_bytes()?;
-    Ok(bin)
+/// A published database row rewritten for the duplicated database.
+pub struct DuplicatedRow {
+  pub pub_row_id: String,
+  pub dup_row_id: String,
+  /// document id of the published row, it has to be duplicated separately
+  pub pub_row_doc_id: Option<String>,
+  /// encoded collab of the duplicated row
+  pub encoded_collab: Vec<u8>,
+}
+
+/// Decodes, rewrites and encodes the published rows of a database, with at most `concurrency`
+/// rows processed at the same time.
+/// `dup_row_ids` maps published row ids to duplicated row ids and must be assigned beforehand,
+/// so the result does not depend on scheduling. Rows are returned sorted by published row id.
+pub async fn duplicate_database_rows(
+  database_row_collabs: HashMap<String, Vec<u8>>,
+  dup_row_ids: Arc<HashMap<String, String>>,
+  new_db_id: String,
+  concurrency: usize,
+) -> Result<Vec<DuplicatedRow>, AppError> {
+  let mut rows = database_row_collabs.into_iter().collect::<Vec<_>>();
+  rows.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+  stream::iter(rows.into_iter().map(|(pub_row_id, row_bin_data)| {
+    let dup_row_ids = dup_row_ids.clone();
+    let new_db_id = new_db_id.clone();
+    async move {
+      tokio::task::spawn_blocking(move || {
+        duplicate_database_row(pub_row_id, row_bin_data, &dup_row_ids, &new_db_id)
+      })
+      .await?
+    }
+  }))
+  .buffered(concurrency.max(1))
+  .try_collect()
+  .await
+}
+
+fn duplicate_database_row(
+  pub_row_id: String,
+  row_bin_data: Vec<u8>,
+  dup_row_ids: &HashMap<String, String>,
+  new_db_id: &str,
+) -> Result<DuplicatedRow, AppError> {
+  let dup_row_id = dup_row_ids
+    .get(&pub_row_id)
+    .ok_or_else(|| AppError::RecordNotFound(format!("row not found: {}", pub_row_id)))?
+    .clone();
+
+  let mut db_row_collab =
+    decode_published_collab(row_bin_data, &dup_row_id, CollabType::DatabaseRow)?;
+  let mut db_row_body = DatabaseRowBody::open(pub_row_id.clone().into(), &mut db_row_collab)
+    .map_err(collab_decode_failed(&pub_row_id, CollabType::DatabaseRow))?;
+
+  let pub_row_doc_id = {
+    let mut txn = db_row_collab.context.transact_mut();
+    // update database_id
+    db_row_body.update(&mut txn, |u| {
+      u.set_database_id(new_db_id.to_string());
+    });
+
+    // get row document id before the id update
+    let pub_row_doc_id = db_row_body
+      .document_id(&txn)
+      .map_err(collab_decode_failed(&pub_row_id, CollabType::DatabaseRow))?;
+
+    // updates row id along with meta keys
+    db_row_body
+      .update_id(&mut txn, dup_row_id.clone().into())
+      .map_err(|e| AppError::Internal(anyhow!("failed to update row id: {:?}", e)))?;
+
+    // "cells": Object {
+    //     "MBaTsr": Object {
+    //         "data": Array [
+    //             String("eefb5700-8cf7-411e-9596-f60b9a51916e"),
+    //             String("23d5e054-42c8-4754-ad69-527e4ffc1e46"),
+    //             // above are published row ids of related database
+    //             // we need to replace them with respective duplicated row ids
+    //         ],
+    //         "field_type": Number(10),
+    //         // use this condition to filter out relation cells
+    //     },
+    // },
+    let cells: MapRef = db_row_body
+      .get_data()
+      .get(&txn, ROW_CELLS)
+      .ok_or_else(|| AppError::RecordNotFound("no cells found in database row collab".to_string()))?
+      .cast()
+      .map_err(|e| AppError::InvalidPublishPayload(format!("row cells is not a map: {:?}", e)))?;
+
+    // collect all cell with field type as relation
+    let mut rel_row_idss = vec![];
+    for (_, out) in cells.iter(&txn) {
+      if let Ok(m) = out.cast::<MapRef>() {
+        if let Some(Out::Any(Any::BigInt(n))) = m.get(&txn, CELL_FIELD_TYPE) {
+          if n == FieldType::Relation as i64 {
+            match m.get(&txn, CELL_DATA) {
+              Some(relation_data) => {
+                if let Ok(arr) = relation_data.cast::<ArrayRef>() {
+                  rel_row_idss.push(arr)
+                };
+              },
+              None => {
+                tracing::warn!("no data found in relation cell, pub_row_id: {}", pub_row_id)
+              },
+            }
+          }
+        }
+      }
+    }
+    // replace all relation cells with duplicated row ids
+    for rel_row_ids in rel_row_idss {
+      let num_refs = rel_row_ids.len(&txn);
+      let mut pub_row_ids = Vec::with_capacity(num_refs as usize);
+      for rel_row_id in rel_row_ids.iter(&txn) {
+        if let Out::Any(Any::String(s)) = rel_row_id {
+          pub_row_ids.push(s);
+        }
+      }
+      rel_row_ids.remove_range(&mut txn, 0, num_refs);
+      for rel_pub_row_id in pub_row_ids {
+        let rel_dup_row_id = dup_row_ids
+          .get(rel_pub_row_id.as_ref())
+          .ok_or_else(|| AppError::RecordNotFound(format!("row not found: {}", rel_pub_row_id)))?;
+        let _ = rel_row_ids.push_back(&mut txn, rel_dup_row_id.as_str());
+      }
+    }
+    pub_row_doc_id
+  };
+
+  let encoded_collab = encode_collab_bin(db_row_collab, CollabType::DatabaseRow)?;
+  Ok(DuplicatedRow {
+    pub_row_id,
+    dup_row_id,
+    pub_row_doc_id,
+    encoded_collab,
   })
-  .await?
+}
+
+/// Decodes the doc state of a published collab, reporting failures as
+/// [AppError::CollabDecodeFailed] instead of an unhandled error.
+fn decode_published_collab(
+  doc_state: Vec<u8>,
+  object_id: &str,
+  collab_type: CollabType,
+) -> Result<Collab, AppError> {
+  Collab::new_with_source(
+    CollabOrigin::Server,
+    object_id,
+    DataSource::DocStateV1(doc_state),
+    vec![],
+    false,
+  )
+  .map_err(collab_decode_failed(object_id, collab_type))
+}
+
+fn collab_decode_failed<E>(object_id: &str, collab_type: CollabType) -> impl FnOnce(E) -> AppError
+where
+  E: std::error::Error + Send + Sync + 'static,
+{
+  let object_id = object_id.to_string();
+  move |err| AppError::CollabDecodeFailed {
+    object_id,
+    collab_type: collab_type.to_string(),
+    source: Box::new(err),
+  }
+}
+
+async fn collab_to_bin(collab: Collab, collab_type: CollabType) -> Result<Vec<u8>, AppError> {
+  tokio::task::spawn_blocking(move || encode_collab_bin(collab, collab_type)).await?
+}
+
+fn encode_collab_bin(collab: Collab, collab_type: CollabType) -> Result<Vec<u8>, AppError> {
+  let bin = collab
+    .encode_collab_v1(|collab| collab_type.validate_require_data(collab))
+    .map_err(|e| AppError::InvalidPublishPayload(e.to_string()))?
+    .encode_to_bytes()?;
+  Ok(bin)
 }
@@ -0,0 +1,198 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use chrono::Utc;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Semaphore;
+use uuid::Uuid;
+
+use app_error::AppError;
+use shared_entity::dto::workspace_dto::{DuplicationState, DuplicationStatus};
+
+use crate::state::RedisConnectionManager;
+
+/// Status of finished jobs is kept for a day, so that clients can still poll it after reconnecting
+const DUPLICATION_JOB_TTL_SECS: u64 = 60 * 60 * 24;
+
+/// How often a queued or running job refreshes its `updated_at`
+const DUPLICATION_JOB_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// A queued or running job without heartbeat for this long is lost, e.g. the server restarted
+const DUPLICATION_JOB_STALE_SECS: i64 = 60;
+
+/// The status is stored in redis, so that it can be polled from any server instance
+#[derive(Serialize, Deserialize)]
+pub struct DuplicationJob {
+  pub workspace_id: String,
+  pub status: DuplicationStatus,
+  /// Unix timestamp in seconds of the last heartbeat of the job
+  pub updated_at: i64,
+}
+
+impl DuplicationJob {
+  fn is_stale(&self) -> bool {
+    matches!(
+      self.status.state,
+      DuplicationState::Queued | DuplicationState::Running
+    ) && Utc::now().timestamp() - self.updated_at > DUPLICATION_JOB_STALE_SECS
+  }
+}
+
+/// Progress of a duplication, reported by the duplicator and stored with its job on every heartbeat
+#[derive(Default)]
+pub struct DuplicationProgress {
+  written_collabs: AtomicUsize,
+  total_collabs: AtomicUsize,
+}
+
+impl DuplicationProgress {
+  /// Starts counting the writes of `total_collabs` collabs, from zero again when the duplication
+  /// is retried
+  pub fn start_writing(&self, total_collabs: usize) {
+    self.written_collabs.store(0, Ordering::Relaxed);
+    self.total_collabs.store(total_collabs, Ordering::Relaxed);
+  }
+
+  pub fn collab_written(&self) {
+    self.written_collabs.fetch_add(1, Ordering::Relaxed);
+  }
+
+  /// Between 0.0 and 1.0, 0.0 until the collabs to write are known
+  pub fn fraction(&self) -> f32 {
+    let total_collabs = self.total_collabs.load(Ordering::Relaxed);
+    if total_collabs == 0 {
+      return 0.0;
+    }
+    let written_collabs = self.written_collabs.load(Ordering::Relaxed);
+    written_collabs.min(total_collabs) as f32 / total_collabs as f32
+  }
+}
+
+fn duplication_job_key(job_id: &Uuid) -> String {
+  format!("af_published_duplicate_job:{}", job_id)
+}
+
+pub async fn set_duplication_job(
+  redis_client: &RedisConnectionManager,
+  job_id: &Uuid,
+  job: &DuplicationJob,
+) -> Result<(), AppError> {
+  let value = serde_json::to_string(job)?;
+  let _: () = redis_client
+    .clone()
+    .set_ex(duplication_job_key(job_id), value, DUPLICATION_JOB_TTL_SECS)
+    .await
+    .map_err(|err| AppError::Internal(anyhow!("Failed to set duplication job: {}", err)))?;
+  Ok(())
+}
+
+pub async fn get_duplication_job(
+  redis_client: &RedisConnectionManager,
+  job_id: &Uuid,
+) -> Result<DuplicationJob, AppError> {
+  let value: Option<String> = redis_client
+    .clone()
+    .get(duplication_job_key(job_id))
+    .await
+    .map_err(|err| AppError::Internal(anyhow!("Failed to get duplication job: {}", err)))?;
+  let value = value
+    .ok_or_else(|| AppError::RecordNotFound(format!("duplication job not found: {}", job_id)))?;
+  let mut job: DuplicationJob = serde_json::from_str(&value)?;
+  if job.is_stale() {
+    job.status.state = DuplicationState::Failed;
+    job.status.error = Some(
+      "The duplication job stopped responding before it was finished, please retry the duplication"
+        .to_string(),
+    );
+  }
+  Ok(job)
+}
+
+/// Queues the `duplication`, which resolves to the id of the duplicated root view, and returns the
+/// job id immediately. At most `semaphore` permits jobs run at the same time, the others stay
+/// queued. The `progress` reported by the duplication is stored along with every heartbeat.
+pub async fn spawn_duplication_job<F>(
+  redis_client: RedisConnectionManager,
+  semaphore: Arc<Semaphore>,
+  workspace_id: String,
+  progress: Arc<DuplicationProgress>,
+  duplication: F,
+) -> Result<Uuid, AppError>
+where
+  F: Future<Output = Result<String, AppError>> + Send + 'static,
+{
+  let job_id = Uuid::new_v4();
+  let mut job = DuplicationJob {
+    workspace_id,
+    status: DuplicationStatus {
+      state: DuplicationState::Queued,
+      progress: 0.0,
+      root_view_id: None,
+      error: None,
+    },
+    updated_at: Utc::now().timestamp(),
+  };
+  set_duplication_job(&redis_client, &job_id, &job).await?;
+
+  tokio::spawn(async move {
+    let permit = with_heartbeat(
+      &redis_client,
+      &job_id,
+      &mut job,
+      &progress,
+      semaphore.acquire_owned(),
+    )
+    .await;
+    // the semaphore is never closed
+    let _permit = permit.expect("duplication job semaphore is closed");
+
+    job.status.state = DuplicationState::Running;
+    match with_heartbeat(&redis_client, &job_id, &mut job, &progress, duplication).await {
+      Ok(root_view_id) => {
+        job.status.state = DuplicationState::Done;
+        job.status.progress = 1.0;
+        job.status.root_view_id = Some(root_view_id);
+      },
+      Err(err) => {
+        tracing::warn!("Duplication job {} failed: {}", job_id, err);
+        job.status.state = DuplicationState::Failed;
+        job.status.error = Some(err.to_string());
+      },
+    }
+    job.updated_at = Utc::now().timestamp();
+    if let Err(err) = set_duplication_job(&redis_client, &job_id, &job).await {
+      tracing::error!("Failed to update duplication job {}: {}", job_id, err);
+    }
+  });
+
+  Ok(job_id)
+}
+
+/// Runs `fut` while storing `job` with a fresh `updated_at` and `progress` every
+/// [DUPLICATION_JOB_HEARTBEAT_INTERVAL], starting right away.
+async fn with_heartbeat<F: Future>(
+  redis_client: &RedisConnectionManager,
+  job_id: &Uuid,
+  job: &mut DuplicationJob,
+  progress: &DuplicationProgress,
+  fut: F,
+) -> F::Output {
+  let mut fut = std::pin::pin!(fut);
+  let mut heartbeat = tokio::time::interval(DUPLICATION_JOB_HEARTBEAT_INTERVAL);
+  loop {
+    tokio::select! {
+      output = &mut fut => return output,
+      _ = heartbeat.tick() => {
+        job.updated_at = Utc::now().timestamp();
+        job.status.progress = progress.fraction();
+        if let Err(err) = set_duplication_job(redis_client, job_id, job).await {
+          tracing::error!("Failed to update duplication job {}: {}", job_id, err);
+        }
+      },
+    }
+  }
+}
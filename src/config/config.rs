@@ -1,4 +1,5 @@
 use std::fmt::Display;
+use std::num::NonZeroUsize;
 use std::str::FromStr;
 
 use anyhow::Context;
@@ -156,6 +157,10 @@ pub enum PublishedCollabStorageBackend {
 #[derive(Clone, Debug)]
 pub struct PublishedCollabSetting {
   pub storage_backend: PublishedCollabStorageBackend,
+  /// Max number of database rows processed in parallel when duplicating a published database
+  pub duplicate_row_concurrency: usize,
+  /// Max number of async duplication jobs running at the same time, the others stay queued
+  pub duplicate_job_concurrency: usize,
 }
 
 impl TryFrom<&str> for PublishedCollabStorageBackend {
@@ -256,6 +261,19 @@ pub fn get_configuration() -> Result<Config, anyhow::Error> {
       storage_backend: get_env_var("APPFLOWY_PUBLISHED_COLLAB_STORAGE_BACKEND", "postgres")
         .as_str()
         .try_into()?,
+      duplicate_row_concurrency: get_env_var(
+        "APPFLOWY_PUBLISHED_COLLAB_DUPLICATE_ROW_CONCURRENCY",
+        "4",
+      )
+      .parse()?,
+      // no job would ever start with a concurrency of 0
+      duplicate_job_concurrency: get_env_var(
+        "APPFLOWY_PUBLISHED_COLLAB_DUPLICATE_JOB_CONCURRENCY",
+        "4",
+      )
+      .parse::<NonZeroUsize>()
+      .context("APPFLOWY_PUBLISHED_COLLAB_DUPLICATE_JOB_CONCURRENCY must be a positive number")?
+      .get(),
     },
     mailer: MailerSetting {
       smtp_host: get_env_var("APPFLOWY_MAILER_SMTP_HOST", "smtp.gmail.com"),
@@ -7,7 +7,7 @@ use dashmap::DashMap;
 use database::collab::cache::CollabCache;
 use secrecy::{ExposeSecret, Secret};
 use sqlx::PgPool;
-use tokio::sync::RwLock;
+use tokio::sync::{RwLock, Semaphore};
 use tokio_stream::StreamExt;
 use uuid::Uuid;
 
@@ -55,6 +55,7 @@ pub struct AppState {
   pub ai_client: AppFlowyAIClient,
   pub grpc_history_client: Arc<Mutex<HistoryClient<tonic::transport::Channel>>>,
   pub indexer_provider: Arc<IndexerProvider>,
+  pub duplication_job_semaphore: Arc<Semaphore>,
 }
 
 impl AppState {
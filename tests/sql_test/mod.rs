@@ -1,4 +1,5 @@
 mod chat_test;
 mod history_test;
+mod policy_test;
 pub(crate) mod util;
 mod workspace_test;
@@ -0,0 +1,196 @@
+use crate::sql_test::util::setup_db;
+
+use access_control::act::{Action, ActionVariant};
+use access_control::casbin::access::AccessControl;
+use access_control::casbin::notification::spawn_listen_on_policy_change;
+use access_control::entity::ObjectType;
+use app_error::AppError;
+use appflowy_cloud::biz::pg_listener::PgListeners;
+use appflowy_cloud::state::AppMetrics;
+use database::policy::{delete_policies_for_subject, delete_policy, upsert_policy};
+use sqlx::PgPool;
+use std::time::Duration;
+
+#[sqlx::test(migrations = false)]
+async fn delete_policy_sql_test(pool: PgPool) {
+  setup_db(&pool).await.unwrap();
+
+  let workspace_object = format!("workspace::{}", uuid::Uuid::new_v4());
+  let collab_object = format!("collab::{}", uuid::Uuid::new_v4());
+  insert_test_policy(&pool, "1", &workspace_object, "member").await;
+  insert_test_policy(&pool, "1", &collab_object, "write").await;
+  insert_test_policy(&pool, "2", &workspace_object, "member").await;
+
+  // only the exact tuple is deleted
+  assert!(delete_policy(&pool, "1", &workspace_object, "member")
+    .await
+    .unwrap());
+  assert!(!delete_policy(&pool, "1", &workspace_object, "member")
+    .await
+    .unwrap());
+  assert!(!delete_policy(&pool, "1", &collab_object, "read")
+    .await
+    .unwrap());
+  assert_eq!(count_policies(&pool, "1").await, 1);
+  assert_eq!(count_policies(&pool, "2").await, 1);
+}
+
+#[sqlx::test(migrations = false)]
+async fn delete_policies_for_subject_sql_test(pool: PgPool) {
+  setup_db(&pool).await.unwrap();
+
+  for i in 0..3 {
+    let object = format!("collab::{}", uuid::Uuid::new_v4());
+    insert_test_policy(&pool, "1", &object, &format!("action_{}", i)).await;
+  }
+  let workspace_object = format!("workspace::{}", uuid::Uuid::new_v4());
+  insert_test_policy(&pool, "2", &workspace_object, "owner").await;
+
+  assert_eq!(delete_policies_for_subject(&pool, "1").await.unwrap(), 3);
+  assert_eq!(count_policies(&pool, "1").await, 0);
+  assert_eq!(count_policies(&pool, "2").await, 1);
+  assert_eq!(delete_policies_for_subject(&pool, "1").await.unwrap(), 0);
+}
+
+#[sqlx::test(migrations = false)]
+async fn upsert_policy_sql_test(pool: PgPool) {
+  setup_db(&pool).await.unwrap();
+
+  let object = format!("workspace::{}", uuid::Uuid::new_v4());
+  upsert_policy(&pool, "1", &object, "member").await.unwrap();
+  assert_eq!(count_policies(&pool, "1").await, 1);
+
+  // putting the same policy again is a no-op
+  upsert_policy(&pool, "1", &object, "member").await.unwrap();
+  assert_eq!(count_policies(&pool, "1").await, 1);
+
+  let collab_object = format!("collab::{}", uuid::Uuid::new_v4());
+  upsert_policy(&pool, "1", &collab_object, "read")
+    .await
+    .unwrap();
+  assert_eq!(count_policies(&pool, "1").await, 2);
+
+  let malformed_objects = [
+    "document::8e062f61-d7ae-4f4b-869c-f44c43149399".to_string(),
+    "workspace:8e062f61-d7ae-4f4b-869c-f44c43149399".to_string(),
+    "collab::not-a-uuid".to_string(),
+    "workspace::".to_string(),
+    String::new(),
+  ];
+  for object in malformed_objects {
+    let err = upsert_policy(&pool, "1", &object, "read")
+      .await
+      .unwrap_err();
+    assert!(
+      matches!(err, AppError::InvalidPolicyObject { .. }),
+      "unexpected error for {:?}: {:?}",
+      object,
+      err
+    );
+  }
+  assert_eq!(count_policies(&pool, "1").await, 2);
+}
+
+#[sqlx::test(migrations = false)]
+async fn policy_write_reloads_access_control_sql_test(pool: PgPool) {
+  setup_db(&pool).await.unwrap();
+  let access_control = AccessControl::new(pool.clone(), AppMetrics::new().access_control_metrics)
+    .await
+    .unwrap();
+
+  let uid = 1;
+  let subject = uid.to_string();
+  let object_id = uuid::Uuid::new_v4().to_string();
+  let object = ObjectType::Collab(&object_id).policy_object();
+  assert!(!can_write(&access_control, uid, &object_id).await);
+
+  // nothing listens to af_policy here, so reload the subject like the policy listener would
+  upsert_policy(&pool, &subject, &object, "l:30")
+    .await
+    .unwrap();
+  access_control.reload_policy(&subject).await.unwrap();
+  assert!(can_write(&access_control, uid, &object_id).await);
+
+  delete_policy(&pool, &subject, &object, "l:30")
+    .await
+    .unwrap();
+  access_control.reload_policy(&subject).await.unwrap();
+  assert!(!can_write(&access_control, uid, &object_id).await);
+
+  // other servers reload the subject once notified of the write
+  let pg_listeners = PgListeners::new(&pool).await.unwrap();
+  spawn_listen_on_policy_change(
+    pg_listeners.subscribe_policy_change(),
+    access_control.clone(),
+  );
+  upsert_policy(&pool, &subject, &object, "l:30")
+    .await
+    .unwrap();
+  let reloaded = tokio::time::timeout(Duration::from_secs(10), async {
+    while !can_write(&access_control, uid, &object_id).await {
+      tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+  })
+  .await;
+  assert!(
+    reloaded.is_ok(),
+    "policy is not reloaded after notification"
+  );
+}
+
+#[sqlx::test(migrations = false)]
+async fn stored_policies_are_loaded_with_the_other_policies_sql_test(pool: PgPool) {
+  setup_db(&pool).await.unwrap();
+
+  let uid = 1;
+  let object_id = uuid::Uuid::new_v4().to_string();
+  let object = ObjectType::Collab(&object_id).policy_object();
+  upsert_policy(&pool, &uid.to_string(), &object, "l:30")
+    .await
+    .unwrap();
+
+  let access_control = AccessControl::new(pool.clone(), AppMetrics::new().access_control_metrics)
+    .await
+    .unwrap();
+  assert!(can_write(&access_control, uid, &object_id).await);
+
+  // removing the stored policy takes it out of the access control
+  delete_policy(&pool, &uid.to_string(), &object, "l:30")
+    .await
+    .unwrap();
+  access_control
+    .reload_policy(&uid.to_string())
+    .await
+    .unwrap();
+  assert!(!can_write(&access_control, uid, &object_id).await);
+}
+
+async fn can_write(access_control: &AccessControl, uid: i64, object_id: &str) -> bool {
+  access_control
+    .enforce(
+      "",
+      &uid,
+      ObjectType::Collab(object_id),
+      ActionVariant::FromAction(&Action::Write),
+    )
+    .await
+    .is_ok()
+}
+
+async fn insert_test_policy(pool: &PgPool, subject: &str, object: &str, action: &str) {
+  sqlx::query("INSERT INTO af_policy (subject, object, action) VALUES ($1, $2, $3)")
+    .bind(subject)
+    .bind(object)
+    .bind(action)
+    .execute(pool)
+    .await
+    .unwrap();
+}
+
+async fn count_policies(pool: &PgPool, subject: &str) -> i64 {
+  sqlx::query_scalar("SELECT COUNT(*) FROM af_policy WHERE subject = $1")
+    .bind(subject)
+    .fetch_one(pool)
+    .await
+    .unwrap()
+}
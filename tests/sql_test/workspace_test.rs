@@ -1,12 +1,15 @@
 use crate::sql_test::util::{generate_random_bytes, setup_db, test_create_user};
 
+use chrono::{DateTime, Duration, TimeZone, Utc};
 use collab_entity::CollabType;
 use database::collab::{
   insert_into_af_collab, insert_into_af_collab_bulk_for_user, select_blob_from_af_collab,
   select_collab_meta_from_af_collab,
 };
+use database::workspace::select_comments_in_range;
 use database_entity::dto::CollabParams;
 use sqlx::PgPool;
+use std::ops::DerefMut;
 
 #[sqlx::test(migrations = false)]
 async fn insert_collab_sql_test(pool: PgPool) {
@@ -60,6 +63,53 @@ async fn insert_collab_sql_test(pool: PgPool) {
     assert!(meta.deleted_at.is_none());
   }
 }
+
+#[sqlx::test(migrations = false)]
+async fn insert_collab_transaction_conflict_sql_test(pool: PgPool) {
+  setup_db(&pool).await.unwrap();
+
+  let user_uuid = uuid::Uuid::new_v4();
+  let name = user_uuid.to_string();
+  let email = format!("{}@appflowy.io", name);
+  let user = test_create_user(&pool, user_uuid, &email, &name)
+    .await
+    .unwrap();
+  let params = CollabParams {
+    object_id: uuid::Uuid::new_v4().to_string(),
+    collab_type: CollabType::Unknown,
+    encoded_collab_v1: generate_random_bytes(1024).into(),
+    embeddings: None,
+  };
+  let mut txn = pool.begin().await.unwrap();
+  insert_into_af_collab(&mut txn, &user.uid, &user.workspace_id, &params)
+    .await
+    .unwrap();
+  txn.commit().await.unwrap();
+
+  // the snapshot of the first transaction is taken before the second one updates the collab
+  let mut txn_1 = pool.begin().await.unwrap();
+  sqlx::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ")
+    .execute(txn_1.deref_mut())
+    .await
+    .unwrap();
+  sqlx::query("SELECT 1 FROM af_collab WHERE oid = $1")
+    .bind(&params.object_id)
+    .execute(txn_1.deref_mut())
+    .await
+    .unwrap();
+  let mut txn_2 = pool.begin().await.unwrap();
+  insert_into_af_collab(&mut txn_2, &user.uid, &user.workspace_id, &params)
+    .await
+    .unwrap();
+  txn_2.commit().await.unwrap();
+
+  // the serialization failure can be retried by the caller
+  let err = insert_into_af_collab(&mut txn_1, &user.uid, &user.workspace_id, &params)
+    .await
+    .unwrap_err();
+  assert!(err.is_transaction_conflict(), "{:?}", err);
+}
+
 #[sqlx::test(migrations = false)]
 async fn insert_bulk_collab_sql_test(pool: PgPool) {
   setup_db(&pool).await.unwrap();
@@ -277,3 +327,93 @@ async fn test_batch_insert_comparison(pool: PgPool) {
     }
   }
 }
+
+#[sqlx::test(migrations = false)]
+async fn select_comments_in_range_sql_test(pool: PgPool) {
+  setup_db(&pool).await.unwrap();
+
+  let user_uuid = uuid::Uuid::new_v4();
+  let user = test_create_user(&pool, user_uuid, "test@appflowy.io", "test_user")
+    .await
+    .unwrap();
+
+  // one comment per hour, the comment at hour 2 is deleted
+  let view_id = uuid::Uuid::new_v4();
+  let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+  for hour in 0..5 {
+    insert_test_comment(
+      &pool,
+      &view_id,
+      user.uid,
+      &format!("comment {}", hour),
+      start + Duration::hours(hour),
+      hour == 2,
+    )
+    .await;
+  }
+  let from = start + Duration::hours(1);
+  let to = start + Duration::hours(3);
+
+  // both bounds are inclusive, deleted comment is excluded
+  let comments = select_comments_in_range(&pool, &from, &to, 10, 0, false)
+    .await
+    .unwrap();
+  let contents: Vec<&str> = comments.iter().map(|c| c.content.as_str()).collect();
+  assert_eq!(contents, vec!["comment 1", "comment 3"]);
+  assert!(comments
+    .iter()
+    .all(|c| c.author_uuid == Some(user_uuid) && c.view_id == view_id));
+
+  // deleted comment is included when asked for
+  let comments = select_comments_in_range(&pool, &from, &to, 10, 0, true)
+    .await
+    .unwrap();
+  let contents: Vec<&str> = comments.iter().map(|c| c.content.as_str()).collect();
+  assert_eq!(contents, vec!["comment 1", "comment 2", "comment 3"]);
+  assert!(comments[1].is_deleted);
+
+  // paging through the whole window
+  let to = start + Duration::hours(4);
+  let first_page = select_comments_in_range(&pool, &start, &to, 2, 0, true)
+    .await
+    .unwrap();
+  let second_page = select_comments_in_range(&pool, &start, &to, 2, 2, true)
+    .await
+    .unwrap();
+  let last_page = select_comments_in_range(&pool, &start, &to, 2, 4, true)
+    .await
+    .unwrap();
+  let contents: Vec<String> = first_page
+    .into_iter()
+    .chain(second_page)
+    .chain(last_page.clone())
+    .map(|c| c.content)
+    .collect();
+  let expected: Vec<String> = (0..5).map(|hour| format!("comment {}", hour)).collect();
+  assert_eq!(contents, expected);
+  assert_eq!(last_page.len(), 1);
+}
+
+async fn insert_test_comment(
+  pool: &PgPool,
+  view_id: &uuid::Uuid,
+  uid: i64,
+  content: &str,
+  created_at: DateTime<Utc>,
+  is_deleted: bool,
+) {
+  sqlx::query(
+    r#"
+      INSERT INTO af_published_view_comment (view_id, created_by, content, created_at, is_deleted)
+      VALUES ($1, $2, $3, $4, $5)
+    "#,
+  )
+  .bind(view_id)
+  .bind(uid)
+  .bind(content)
+  .bind(created_at)
+  .bind(is_deleted)
+  .execute(pool)
+  .await
+  .unwrap();
+}
@@ -1,29 +1,71 @@
-use app_error::ErrorCode;
+use access_control::noops::collab::CollabAccessControlImpl as NoOpsCollabAccessControlImpl;
+use access_control::noops::workspace::WorkspaceAccessControlImpl as NoOpsWorkspaceAccessControlImpl;
+use app_error::{AppError, ErrorCode};
 use appflowy_cloud::biz::collab::folder_view::collab_folder_to_folder_view;
-use appflowy_cloud::biz::workspace::ops::collab_from_doc_state;
+use appflowy_cloud::biz::workspace::ops::{
+  broadcast_update, collab_from_doc_state, NoOpGroupBroadcaster,
+};
+use appflowy_cloud::biz::workspace::publish_dup::{
+  collect_page_mention_ids, collect_reachable_published_views, duplicate_database_rows,
+  duplicate_published_collab_to_workspace, insert_collab_for_duplicator, insert_views_to_folder,
+  parse_text_map_value, remap_page_mention_ids, retry_on_transaction_conflict, CollisionPolicy,
+  DuplicatorCollabStorage, ReachableView,
+};
+use appflowy_cloud::biz::workspace::publish_dup_job::{
+  get_duplication_job, set_duplication_job, spawn_duplication_job, DuplicationJob,
+  DuplicationProgress,
+};
+use appflowy_cloud::config::config::get_configuration;
+use appflowy_cloud::state::AppMetrics;
+use appflowy_collaborate::collab::access_control::CollabStorageAccessControlImpl;
+use appflowy_collaborate::collab::storage::{CollabAccessControlStorage, CollabStorageImpl};
+use appflowy_collaborate::command::{
+  CLCommandReceiver, CollaborationCommand, SERVER_MESSAGE_ACK_TIMEOUT,
+};
+use appflowy_collaborate::snapshot::SnapshotControl;
+use async_trait::async_trait;
 use client_api::entity::{
   AFRole, GlobalComment, PatchPublishedCollab, PublishCollabItem, PublishCollabMetadata,
   PublishInfoMeta,
 };
 use client_api_test::TestClient;
 use client_api_test::{generate_unique_registered_user_client, localhost_client};
+use collab::entity::EncodedCollab;
+use collab::preclude::Collab;
 use collab::util::MapExt;
-use collab_database::database::DatabaseBody;
+use collab_database::database::{gen_row_id, DatabaseBody};
 use collab_database::entity::FieldType;
 use collab_database::rows::RowDetail;
 use collab_database::views::DatabaseViews;
 use collab_database::workspace_database::{NoPersistenceDatabaseCollabService, WorkspaceDatabase};
 use collab_document::document::Document;
 use collab_entity::CollabType;
-use collab_folder::{CollabOrigin, Folder, UserId};
+use collab_folder::{
+  CollabOrigin, Folder, FolderData, RepeatedViewIdentifier, UserId, View, ViewLayout, Workspace,
+};
+use collab_rt_entity::ClientCollabMessage;
+use database::collab::cache::CollabCache;
+use database::collab::{is_collab_exists, select_blob_from_af_collab};
+use database_entity::dto::{CollabParams, CreateCollabParams, QueryCollab, QueryCollabParams};
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use shared_entity::dto::publish_dto::PublishDatabaseData;
+use shared_entity::dto::workspace_dto::{
+  DuplicationState, DuplicationStatus, DuplicationStrategy, FolderView, PublishedDuplicate,
+  ViewLayout as PublishedViewLayout,
+};
+use sqlx::postgres::PgPoolOptions;
+use sqlx::{PgPool, Postgres, Transaction};
+use std::borrow::Borrow;
 use std::collections::{HashMap, HashSet};
+use std::ops::DerefMut;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::thread::sleep;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use yrs::{ReadTxn, StateVector, Text, Transact};
 
+use crate::collab::util::{redis_connection_manager, test_encode_collab_v1};
 use crate::workspace::published_data::{self};
 
 #[tokio::test]
@@ -1232,6 +1274,322 @@ async fn duplicate_to_workspace_db_embedded_in_doc() {
   }
 }
 
+#[tokio::test]
+async fn duplicate_to_workspace_db_embedded_in_doc_linkage() {
+  let client_1 = TestClient::new_user().await;
+  let workspace_id = client_1.workspace_id().await;
+
+  let doc_with_embedded_db_view_id: uuid::Uuid = uuid::Uuid::new_v4();
+  client_1
+    .publish_collabs(
+      &workspace_id,
+      vec![
+        (
+          doc_with_embedded_db_view_id,
+          published_data::DOC_WITH_EMBEDDED_DB_META,
+          published_data::DOC_WITH_EMBEDDED_DB_HEX,
+        ),
+        (
+          "bb221175-14da-4a05-a09d-595e42d2350f".parse().unwrap(),
+          published_data::EMBEDDED_DB_META,
+          published_data::EMBEDDED_DB_HEX,
+        ),
+      ],
+    )
+    .await;
+
+  {
+    let client_2 = TestClient::new_user().await;
+    let workspace_id_2 = client_2.workspace_id().await;
+    let fv = client_2
+      .api_client
+      .get_workspace_folder(&workspace_id_2, Some(5), None)
+      .await
+      .unwrap();
+
+    // must not fail with "workspace database not found"
+    client_2
+      .api_client
+      .duplicate_published_to_workspace(
+        &workspace_id_2,
+        &PublishedDuplicate {
+          published_view_id: doc_with_embedded_db_view_id.to_string(),
+          dest_view_id: fv.view_id,
+          strategy: DuplicationStrategy::Deep,
+          run_async: false,
+        },
+      )
+      .await
+      .unwrap();
+    tokio::time::sleep(Duration::from_secs(1)).await;
+
+    let fv = client_2
+      .api_client
+      .get_workspace_folder(&workspace_id_2, Some(5), None)
+      .await
+      .unwrap();
+    let doc_with_embedded_db = fv
+      .children
+      .into_iter()
+      .find(|v| v.name == "docwithembeddeddb")
+      .unwrap();
+    let doc_collab = client_2
+      .get_collab_to_collab(
+        workspace_id_2.clone(),
+        doc_with_embedded_db.view_id.clone(),
+        CollabType::Document,
+      )
+      .await
+      .unwrap();
+    let doc_data = Document::open(doc_collab)
+      .unwrap()
+      .get_document_data()
+      .unwrap();
+    let grid = doc_data
+      .blocks
+      .iter()
+      .find(|(_k, b)| b.ty == "grid")
+      .unwrap()
+      .1;
+    let grid_view_id = grid.data.get("view_id").unwrap().as_str().unwrap();
+
+    // the view referenced by the block is linked to the duplicated database
+    let ws_db_collab = client_2
+      .get_workspace_database_collab(&workspace_id_2)
+      .await;
+    let dup_db_id = WorkspaceDatabase::open(ws_db_collab)
+      .unwrap()
+      .get_all_database_meta()
+      .into_iter()
+      .find(|db_meta| db_meta.linked_views.iter().any(|v| v == grid_view_id))
+      .unwrap()
+      .database_id;
+    let db_collab = client_2
+      .get_collab_to_collab(
+        workspace_id_2.clone(),
+        dup_db_id.clone(),
+        CollabType::Database,
+      )
+      .await
+      .unwrap();
+    assert_eq!(
+      DatabaseBody::database_id_from_collab(&db_collab).unwrap(),
+      dup_db_id
+    );
+  }
+}
+
+#[tokio::test]
+async fn duplicate_to_workspace_db_embedded_in_doc_shallow() {
+  let client_1 = TestClient::new_user().await;
+  let workspace_id = client_1.workspace_id().await;
+  let embedded_db_view_id = "bb221175-14da-4a05-a09d-595e42d2350f";
+
+  // document and database are published from, and duplicated into, the same workspace
+  let doc_with_embedded_db_view_id: uuid::Uuid = uuid::Uuid::new_v4();
+  client_1
+    .publish_collabs(
+      &workspace_id,
+      vec![
+        (
+          doc_with_embedded_db_view_id,
+          published_data::DOC_WITH_EMBEDDED_DB_META,
+          published_data::DOC_WITH_EMBEDDED_DB_HEX,
+        ),
+        (
+          embedded_db_view_id.parse().unwrap(),
+          published_data::EMBEDDED_DB_META,
+          published_data::EMBEDDED_DB_HEX,
+        ),
+      ],
+    )
+    .await;
+
+  let fv = client_1
+    .api_client
+    .get_workspace_folder(&workspace_id, Some(5), None)
+    .await
+    .unwrap();
+  let database_ids = |ws_db_collab: Collab| {
+    WorkspaceDatabase::open(ws_db_collab)
+      .unwrap()
+      .get_all_database_meta()
+      .into_iter()
+      .map(|db_meta| db_meta.database_id)
+      .collect::<HashSet<_>>()
+  };
+  let db_ids_before = database_ids(client_1.get_workspace_database_collab(&workspace_id).await);
+  let pg_pool = local_pg_pool().await;
+  let db_collab_count_before = count_database_collabs(&pg_pool, &workspace_id).await;
+
+  // shallow: the document links to the shared database
+  client_1
+    .api_client
+    .duplicate_published_to_workspace(
+      &workspace_id,
+      &PublishedDuplicate {
+        published_view_id: doc_with_embedded_db_view_id.to_string(),
+        dest_view_id: fv.view_id.clone(),
+        strategy: DuplicationStrategy::ShallowLinkDatabases,
+        run_async: false,
+      },
+    )
+    .await
+    .unwrap();
+  tokio::time::sleep(Duration::from_secs(1)).await;
+  {
+    let fv = client_1
+      .api_client
+      .get_workspace_folder(&workspace_id, Some(5), None)
+      .await
+      .unwrap();
+    let doc_with_embedded_db = fv
+      .children
+      .into_iter()
+      .find(|v| v.name == "docwithembeddeddb")
+      .unwrap();
+    let doc_collab = client_1
+      .get_collab_to_collab(
+        workspace_id.clone(),
+        doc_with_embedded_db.view_id.clone(),
+        CollabType::Document,
+      )
+      .await
+      .unwrap();
+    let doc_data = Document::open(doc_collab)
+      .unwrap()
+      .get_document_data()
+      .unwrap();
+    let grid = doc_data
+      .blocks
+      .iter()
+      .find(|(_k, b)| b.ty == "grid")
+      .unwrap()
+      .1;
+    assert_eq!(
+      grid.data.get("view_id").unwrap().as_str().unwrap(),
+      embedded_db_view_id
+    );
+
+    // no new database or database row is created
+    let db_ids = database_ids(client_1.get_workspace_database_collab(&workspace_id).await);
+    assert_eq!(db_ids, db_ids_before);
+    assert_eq!(
+      count_database_collabs(&pg_pool, &workspace_id).await,
+      db_collab_count_before
+    );
+  }
+
+  // deep: the database is copied along with its rows
+  client_1
+    .duplicate_published_to_workspace(
+      &workspace_id,
+      &doc_with_embedded_db_view_id.to_string(),
+      &fv.view_id,
+    )
+    .await;
+  {
+    let db_ids = database_ids(client_1.get_workspace_database_collab(&workspace_id).await);
+    let new_db_ids = db_ids.difference(&db_ids_before).collect::<Vec<_>>();
+    assert_eq!(new_db_ids.len(), 1);
+
+    let new_db_id = new_db_ids[0].clone();
+    let db_collab = client_1
+      .get_collab_to_collab(workspace_id.clone(), new_db_id, CollabType::Database)
+      .await
+      .unwrap();
+    let txn = db_collab.transact();
+    let view_map = {
+      let map_ref = db_collab
+        .data
+        .get_with_path(&txn, ["database", "views"])
+        .unwrap();
+      DatabaseViews::new(CollabOrigin::Empty, map_ref, None)
+    };
+    let row_ids = view_map
+      .get_all_views(&txn)
+      .into_iter()
+      .flat_map(|v| v.row_orders)
+      .map(|row_order| row_order.id.to_string())
+      .collect::<HashSet<_>>();
+    assert!(!row_ids.is_empty());
+    for row_id in row_ids {
+      client_1
+        .get_collab_to_collab(workspace_id.clone(), row_id, CollabType::DatabaseRow)
+        .await
+        .unwrap();
+    }
+  }
+}
+
+#[tokio::test]
+async fn duplicate_to_workspace_db_embedded_in_doc_shallow_unpublished_db() {
+  let client_1 = TestClient::new_user().await;
+  let workspace_id = client_1.workspace_id().await;
+
+  // the embedded database is not published, only the document is
+  let doc_with_embedded_db_view_id: uuid::Uuid = uuid::Uuid::new_v4();
+  client_1
+    .publish_collabs(
+      &workspace_id,
+      vec![(
+        doc_with_embedded_db_view_id,
+        published_data::DOC_WITH_EMBEDDED_DB_META,
+        published_data::DOC_WITH_EMBEDDED_DB_HEX,
+      )],
+    )
+    .await;
+
+  let fv = client_1
+    .api_client
+    .get_workspace_folder(&workspace_id, Some(5), None)
+    .await
+    .unwrap();
+  let pg_pool = local_pg_pool().await;
+  let db_collab_count_before = count_database_collabs(&pg_pool, &workspace_id).await;
+
+  // like a deep duplication, the unpublished database is skipped
+  client_1
+    .api_client
+    .duplicate_published_to_workspace(
+      &workspace_id,
+      &PublishedDuplicate {
+        published_view_id: doc_with_embedded_db_view_id.to_string(),
+        dest_view_id: fv.view_id,
+        strategy: DuplicationStrategy::ShallowLinkDatabases,
+        run_async: false,
+      },
+    )
+    .await
+    .unwrap();
+  tokio::time::sleep(Duration::from_secs(1)).await;
+
+  let fv = client_1
+    .api_client
+    .get_workspace_folder(&workspace_id, Some(5), None)
+    .await
+    .unwrap();
+  assert!(fv.children.iter().any(|v| v.name == "docwithembeddeddb"));
+  assert_eq!(
+    count_database_collabs(&pg_pool, &workspace_id).await,
+    db_collab_count_before
+  );
+}
+
+/// Number of Database and DatabaseRow collabs stored in the workspace
+async fn count_database_collabs(pg_pool: &PgPool, workspace_id: &str) -> i64 {
+  sqlx::query_scalar(
+    "SELECT COUNT(*) FROM af_collab WHERE workspace_id = $1 AND partition_key IN ($2, $3)",
+  )
+  .bind(uuid::Uuid::parse_str(workspace_id).unwrap())
+  // partition keys of CollabType::Database and CollabType::DatabaseRow
+  .bind(1)
+  .bind(4)
+  .fetch_one(pg_pool)
+  .await
+  .unwrap()
+}
+
 #[tokio::test]
 async fn duplicate_to_workspace_db_with_relation() {
   let client_1 = TestClient::new_user().await;
@@ -1584,10 +1942,1464 @@ async fn duplicate_to_workspace_inline_db_doc_with_relation() {
   }
 }
 
-fn get_database_id_and_row_ids(published_db_blob: &[u8]) -> (String, HashSet<String>) {
-  let pub_db_data = serde_json::from_slice::<PublishDatabaseData>(published_db_blob).unwrap();
-  let db_collab = collab_from_doc_state(pub_db_data.database_collab, "").unwrap();
-  let pub_db_id = DatabaseBody::database_id_from_collab(&db_collab).unwrap();
-  let row_ids: HashSet<String> = pub_db_data.database_row_collabs.into_keys().collect();
-  (pub_db_id, row_ids)
+#[tokio::test]
+async fn duplicate_to_workspace_corrupt_doc_blob() {
+  let client_1 = TestClient::new_user().await;
+  let workspace_id = client_1.workspace_id().await;
+
+  // document published with a doc state that cannot be decoded
+  let corrupt_doc_view_id = uuid::Uuid::new_v4();
+  client_1
+    .publish_collabs(
+      &workspace_id,
+      vec![(
+        corrupt_doc_view_id,
+        published_data::DOC_2_META,
+        "deadbeefdeadbeef",
+      )],
+    )
+    .await;
+
+  {
+    let client_2 = TestClient::new_user().await;
+    let workspace_id_2 = client_2.workspace_id().await;
+    let fv = client_2
+      .api_client
+      .get_workspace_folder(&workspace_id_2, Some(5), None)
+      .await
+      .unwrap();
+
+    let err = client_2
+      .api_client
+      .duplicate_published_to_workspace(
+        &workspace_id_2,
+        &PublishedDuplicate {
+          published_view_id: corrupt_doc_view_id.to_string(),
+          dest_view_id: fv.view_id,
+          strategy: DuplicationStrategy::Deep,
+          run_async: false,
+        },
+      )
+      .await
+      .unwrap_err();
+    assert_eq!(err.code, ErrorCode::CollabDecodeFailed);
+  }
+}
+
+#[tokio::test]
+async fn duplicate_to_workspace_async_running_to_done() {
+  let client_1 = TestClient::new_user().await;
+  let workspace_id = client_1.workspace_id().await;
+
+  let doc_view_id = uuid::Uuid::new_v4();
+  client_1
+    .publish_collabs(
+      &workspace_id,
+      vec![(
+        doc_view_id,
+        published_data::DOC_2_META,
+        published_data::DOC_2_DOC_STATE_HEX,
+      )],
+    )
+    .await;
+
+  let client_2 = TestClient::new_user().await;
+  let workspace_id_2 = client_2.workspace_id().await;
+  let fv = client_2
+    .api_client
+    .get_workspace_folder(&workspace_id_2, Some(5), None)
+    .await
+    .unwrap();
+
+  let job_id = client_2
+    .api_client
+    .duplicate_published_to_workspace_async(
+      &workspace_id_2,
+      &PublishedDuplicate {
+        published_view_id: doc_view_id.to_string(),
+        dest_view_id: fv.view_id,
+        strategy: DuplicationStrategy::Deep,
+        run_async: true,
+      },
+    )
+    .await
+    .unwrap();
+
+  let status = wait_for_duplication(&client_2, &job_id).await;
+  assert_eq!(status.state, DuplicationState::Done);
+  assert_eq!(status.progress, 1.0);
+  assert!(status.error.is_none());
+  let root_view_id = status.root_view_id.unwrap();
+
+  let fv = client_2
+    .api_client
+    .get_workspace_folder(&workspace_id_2, Some(5), None)
+    .await
+    .unwrap();
+  let doc_2_fv = fv
+    .children
+    .iter()
+    .find(|v| v.view_id == root_view_id)
+    .expect("duplicated view not found");
+  assert_eq!(doc_2_fv.name, "doc2");
+}
+
+#[tokio::test]
+async fn duplicate_to_workspace_async_failed() {
+  let client_1 = TestClient::new_user().await;
+  let workspace_id = client_1.workspace_id().await;
+
+  let corrupt_doc_view_id = uuid::Uuid::new_v4();
+  client_1
+    .publish_collabs(
+      &workspace_id,
+      vec![(
+        corrupt_doc_view_id,
+        published_data::DOC_2_META,
+        "deadbeefdeadbeef",
+      )],
+    )
+    .await;
+
+  let client_2 = TestClient::new_user().await;
+  let workspace_id_2 = client_2.workspace_id().await;
+  let fv = client_2
+    .api_client
+    .get_workspace_folder(&workspace_id_2, Some(5), None)
+    .await
+    .unwrap();
+
+  // the request itself succeeds, the failure is reported through the job status
+  let job_id = client_2
+    .api_client
+    .duplicate_published_to_workspace_async(
+      &workspace_id_2,
+      &PublishedDuplicate {
+        published_view_id: corrupt_doc_view_id.to_string(),
+        dest_view_id: fv.view_id,
+        strategy: DuplicationStrategy::Deep,
+        run_async: true,
+      },
+    )
+    .await
+    .unwrap();
+
+  let status = wait_for_duplication(&client_2, &job_id).await;
+  assert_eq!(status.state, DuplicationState::Failed);
+  assert!(status.root_view_id.is_none());
+  let error = status.error.unwrap();
+  assert!(error.contains("Failed to decode"), "{}", error);
+
+  // the job is not visible to users outside of the workspace
+  let client_3 = TestClient::new_user().await;
+  client_3
+    .api_client
+    .get_duplication_status(&job_id)
+    .await
+    .unwrap_err();
+}
+
+#[tokio::test]
+async fn duplicate_to_workspace_async_stale_job() {
+  let client = TestClient::new_user().await;
+  let workspace_id = client.workspace_id().await;
+
+  // a running job whose server stopped before finishing it, e.g. because of a restart
+  let job_id = uuid::Uuid::new_v4();
+  let job = DuplicationJob {
+    workspace_id,
+    status: DuplicationStatus {
+      state: DuplicationState::Running,
+      progress: 0.5,
+      root_view_id: None,
+      error: None,
+    },
+    updated_at: chrono::Utc::now().timestamp() - 60 * 10,
+  };
+  set_duplication_job(&redis_connection_manager().await, &job_id, &job)
+    .await
+    .unwrap();
+
+  let status = client
+    .api_client
+    .get_duplication_status(&job_id.to_string())
+    .await
+    .unwrap();
+  assert_eq!(status.state, DuplicationState::Failed);
+  let error = status.error.unwrap();
+  assert!(error.contains("stopped responding"), "{}", error);
+}
+
+#[tokio::test]
+async fn duplicate_to_workspace_async_progress() {
+  let redis_client = redis_connection_manager().await;
+  let workspace_id = uuid::Uuid::new_v4().to_string();
+  let progress = Arc::new(DuplicationProgress::default());
+  let (finish_tx, finish_rx) = tokio::sync::oneshot::channel::<()>();
+
+  // half of the collabs are written, the rest waits for the test
+  let job_progress = progress.clone();
+  let job_id = spawn_duplication_job(
+    redis_client.clone(),
+    Arc::new(tokio::sync::Semaphore::new(1)),
+    workspace_id,
+    progress,
+    async move {
+      job_progress.start_writing(4);
+      job_progress.collab_written();
+      job_progress.collab_written();
+      finish_rx.await.unwrap();
+      Ok("root_view_id".to_string())
+    },
+  )
+  .await
+  .unwrap();
+
+  // the progress is stored with the next heartbeat
+  let mut status = get_duplication_job(&redis_client, &job_id)
+    .await
+    .unwrap()
+    .status;
+  for _ in 0..30 {
+    if status.progress > 0.0 {
+      break;
+    }
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    status = get_duplication_job(&redis_client, &job_id)
+      .await
+      .unwrap()
+      .status;
+  }
+  assert_eq!(status.state, DuplicationState::Running);
+  assert_eq!(status.progress, 0.5);
+
+  finish_tx.send(()).unwrap();
+  for _ in 0..20 {
+    if status.state == DuplicationState::Done {
+      break;
+    }
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    status = get_duplication_job(&redis_client, &job_id)
+      .await
+      .unwrap()
+      .status;
+  }
+  assert_eq!(status.state, DuplicationState::Done);
+  assert_eq!(status.progress, 1.0);
+  assert_eq!(status.root_view_id.as_deref(), Some("root_view_id"));
+}
+
+#[tokio::test]
+async fn duplicate_to_workspace_unpublished_view() {
+  let client_1 = TestClient::new_user().await;
+  let workspace_id = client_1.workspace_id().await;
+  let fv = client_1
+    .api_client
+    .get_workspace_folder(&workspace_id, Some(5), None)
+    .await
+    .unwrap();
+
+  // the document stays in the workspace after it is unpublished
+  let view_id = uuid::Uuid::new_v4();
+  let unpublished_view_id = view_id.to_string();
+  client_1
+    .api_client
+    .create_collab(CreateCollabParams {
+      workspace_id: workspace_id.clone(),
+      object_id: unpublished_view_id.clone(),
+      encoded_collab_v1: test_encode_collab_v1(&unpublished_view_id, "title", "doc")
+        .encode_to_bytes()
+        .unwrap(),
+      collab_type: CollabType::Unknown,
+    })
+    .await
+    .unwrap();
+  client_1
+    .publish_collabs(
+      &workspace_id,
+      vec![(
+        view_id,
+        published_data::DOC_2_META,
+        published_data::DOC_2_DOC_STATE_HEX,
+      )],
+    )
+    .await;
+  client_1
+    .api_client
+    .unpublish_collabs(&workspace_id, &[view_id])
+    .await
+    .unwrap();
+
+  let err = client_1
+    .api_client
+    .duplicate_published_to_workspace(
+      &workspace_id,
+      &PublishedDuplicate {
+        published_view_id: unpublished_view_id.clone(),
+        dest_view_id: fv.view_id.clone(),
+        strategy: DuplicationStrategy::Deep,
+        run_async: false,
+      },
+    )
+    .await
+    .unwrap_err();
+  assert!(err.is_view_not_published(), "{:?}", err);
+  assert!(!err.is_record_not_found());
+  assert!(
+    err.message.contains(&unpublished_view_id),
+    "{}",
+    err.message
+  );
+
+  // async duplication reports the same error through the job status
+  let job_id = client_1
+    .api_client
+    .duplicate_published_to_workspace_async(
+      &workspace_id,
+      &PublishedDuplicate {
+        published_view_id: unpublished_view_id.clone(),
+        dest_view_id: fv.view_id.clone(),
+        strategy: DuplicationStrategy::Deep,
+        run_async: true,
+      },
+    )
+    .await
+    .unwrap();
+  let status = wait_for_duplication(&client_1, &job_id).await;
+  assert_eq!(status.state, DuplicationState::Failed);
+  let error = status.error.unwrap();
+  assert!(error.contains("is not published"), "{}", error);
+
+  // an id that was never published is not found
+  let err = client_1
+    .api_client
+    .duplicate_published_to_workspace(
+      &workspace_id,
+      &PublishedDuplicate {
+        published_view_id: uuid::Uuid::new_v4().to_string(),
+        dest_view_id: fv.view_id,
+        strategy: DuplicationStrategy::Deep,
+        run_async: false,
+      },
+    )
+    .await
+    .unwrap_err();
+  assert!(err.is_record_not_found(), "{:?}", err);
+  assert!(!err.is_view_not_published());
+}
+
+/// Polls the duplication status until the job is finished, checking that the state only moves
+/// forward along queued -> running -> done/failed, and that the progress never goes back.
+async fn wait_for_duplication(client: &TestClient, job_id: &str) -> DuplicationStatus {
+  let mut last_state = DuplicationState::Queued;
+  let mut last_progress = 0.0;
+  for _ in 0..60 {
+    let status = client
+      .api_client
+      .get_duplication_status(job_id)
+      .await
+      .unwrap();
+    assert!(
+      (last_progress..=1.0).contains(&status.progress),
+      "unexpected duplication progress: {} -> {}",
+      last_progress,
+      status.progress
+    );
+    last_progress = status.progress;
+    match (last_state, status.state) {
+      (_, DuplicationState::Done | DuplicationState::Failed) => return status,
+      (DuplicationState::Queued, _) | (DuplicationState::Running, DuplicationState::Running) => {
+        last_state = status.state;
+      },
+      (from, to) => panic!(
+        "unexpected duplication state transition: {:?} -> {:?}",
+        from, to
+      ),
+    }
+    tokio::time::sleep(Duration::from_millis(500)).await;
+  }
+  panic!("duplication job {} is not finished in time", job_id);
+}
+
+#[tokio::test]
+async fn duplicate_to_workspace_without_group_broadcaster() {
+  let client_1 = TestClient::new_user().await;
+  let workspace_id = client_1.workspace_id().await;
+
+  let doc_view_id = uuid::Uuid::new_v4();
+  client_1
+    .publish_collabs(
+      &workspace_id,
+      vec![(
+        doc_view_id,
+        published_data::DOC_2_META,
+        published_data::DOC_2_DOC_STATE_HEX,
+      )],
+    )
+    .await;
+
+  let client_2 = TestClient::new_user().await;
+  let workspace_id_2 = client_2.workspace_id().await;
+  let uid_2 = client_2.uid().await;
+  let fv = client_2
+    .api_client
+    .get_workspace_folder(&workspace_id_2, Some(5), None)
+    .await
+    .unwrap();
+
+  // duplicate straight through the library, without any realtime server, collab group or bucket
+  let (pg_pool, collab_cache) = local_collab_cache().await;
+  let root_view_id = duplicate_published_collab_to_workspace(
+    &pg_pool,
+    None,
+    Arc::new(collab_cache),
+    Arc::new(NoOpGroupBroadcaster),
+    uid_2,
+    doc_view_id.to_string(),
+    workspace_id_2.clone(),
+    fv.view_id.clone(),
+    1,
+    DuplicationStrategy::Deep,
+    CollisionPolicy::Error,
+    Arc::new(DuplicationProgress::default()),
+  )
+  .await
+  .unwrap();
+
+  let fv = client_2
+    .api_client
+    .get_workspace_folder(&workspace_id_2, Some(5), None)
+    .await
+    .unwrap();
+  let doc_2_fv = fv
+    .children
+    .iter()
+    .find(|v| v.name == "doc2")
+    .expect("doc2 not found");
+  assert_ne!(doc_2_fv.view_id, doc_view_id.to_string());
+  assert_eq!(doc_2_fv.view_id, root_view_id);
+  client_2
+    .api_client
+    .get_collab(QueryCollabParams {
+      workspace_id: workspace_id_2,
+      inner: QueryCollab {
+        object_id: doc_2_fv.view_id.clone(),
+        collab_type: CollabType::Document,
+      },
+    })
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn duplicate_to_workspace_update_reaches_connected_editor() {
+  let client_1 = TestClient::new_user().await;
+  let workspace_id = client_1.workspace_id().await;
+
+  let doc_view_id = uuid::Uuid::new_v4();
+  client_1
+    .publish_collabs(
+      &workspace_id,
+      vec![(
+        doc_view_id,
+        published_data::DOC_2_META,
+        published_data::DOC_2_DOC_STATE_HEX,
+      )],
+    )
+    .await;
+
+  let mut client_2 = TestClient::new_user().await;
+  let workspace_id_2 = client_2.workspace_id().await;
+  // editing the folder creates its group, which has to apply the duplicated views
+  client_2
+    .open_collab(&workspace_id_2, &workspace_id_2, CollabType::Folder)
+    .await;
+  let fv = client_2
+    .api_client
+    .get_workspace_folder(&workspace_id_2, Some(5), None)
+    .await
+    .unwrap();
+
+  client_2
+    .api_client
+    .duplicate_published_to_workspace(
+      &workspace_id_2,
+      &PublishedDuplicate {
+        published_view_id: doc_view_id.to_string(),
+        dest_view_id: fv.view_id.clone(),
+        strategy: DuplicationStrategy::Deep,
+        run_async: false,
+      },
+    )
+    .await
+    .unwrap();
+
+  // the editor is not reloaded, it receives the views applied by the group
+  let mut view_names = vec![];
+  for _ in 0..20 {
+    view_names = local_folder_child_view_names(&client_2, &workspace_id_2, &fv.view_id).await;
+    if view_names.iter().any(|name| name == "doc2") {
+      break;
+    }
+    tokio::time::sleep(Duration::from_millis(500)).await;
+  }
+  assert!(
+    view_names.iter().any(|name| name == "doc2"),
+    "{:?}",
+    view_names
+  );
+}
+
+#[tokio::test]
+async fn broadcast_update_confirmed_by_group() {
+  let (collab_storage, mut rt_cmd_rx) = local_collab_storage_with_realtime_server().await;
+  let object_id = uuid::Uuid::new_v4().to_string();
+
+  // the group of the object applies the update and acks it
+  let expected_object_id = object_id.clone();
+  let group = tokio::spawn(async move {
+    match rt_cmd_rx.recv().await {
+      Some(CollaborationCommand::ServerSendCollabMessage {
+        object_id,
+        collab_messages,
+        ret,
+      }) => {
+        assert_eq!(object_id, expected_object_id);
+        assert!(matches!(
+          collab_messages.as_slice(),
+          [ClientCollabMessage::ClientUpdateSync { .. }]
+        ));
+        ret.send(Ok(true)).unwrap();
+      },
+      _ => panic!("expected a server message for the group"),
+    }
+  });
+
+  let delivered = broadcast_update(&collab_storage, &object_id, test_update())
+    .await
+    .unwrap();
+  assert!(delivered);
+  group.await.unwrap();
+}
+
+#[tokio::test]
+async fn broadcast_update_not_confirmed_by_group() {
+  let (collab_storage, mut rt_cmd_rx) = local_collab_storage_with_realtime_server().await;
+  let object_id = uuid::Uuid::new_v4().to_string();
+
+  // the group of the object never acks the update
+  let _group = tokio::spawn(async move {
+    if let Some(CollaborationCommand::ServerSendCollabMessage { ret, .. }) = rt_cmd_rx.recv().await
+    {
+      tokio::time::sleep(SERVER_MESSAGE_ACK_TIMEOUT * 10).await;
+      drop(ret);
+    }
+  });
+
+  let started_at = Instant::now();
+  let delivered = broadcast_update(&collab_storage, &object_id, test_update())
+    .await
+    .unwrap();
+  assert!(!delivered);
+  assert!(started_at.elapsed() >= SERVER_MESSAGE_ACK_TIMEOUT);
+}
+
+/// Builds the collab storage of the local environment. The realtime server is played by the
+/// caller, through the returned receiver of the realtime commands.
+async fn local_collab_storage_with_realtime_server(
+) -> (CollabAccessControlStorage, CLCommandReceiver) {
+  let (pg_pool, collab_cache) = local_collab_cache().await;
+  let metrics = AppMetrics::new();
+  let (rt_cmd_tx, rt_cmd_rx) = tokio::sync::mpsc::channel(10);
+  let access_control = CollabStorageAccessControlImpl {
+    collab_access_control: Arc::new(NoOpsCollabAccessControlImpl::new()),
+    workspace_access_control: Arc::new(NoOpsWorkspaceAccessControlImpl::new()),
+    cache: collab_cache.clone(),
+  };
+  let snapshot_control = SnapshotControl::new(
+    redis_connection_manager().await,
+    pg_pool,
+    metrics.collab_metrics.clone(),
+  )
+  .await;
+  let collab_storage = CollabStorageImpl::new(
+    collab_cache,
+    access_control,
+    snapshot_control,
+    rt_cmd_tx,
+    redis_connection_manager().await,
+    metrics.collab_metrics,
+  );
+  (collab_storage, rt_cmd_rx)
+}
+
+fn test_update() -> Vec<u8> {
+  let doc = yrs::Doc::new();
+  let text = doc.get_or_insert_text("text");
+  text.push(&mut doc.transact_mut(), "hello");
+  let txn = doc.transact();
+  txn.encode_state_as_update_v1(&StateVector::default())
+}
+
+/// Names of the children of `parent_view_id` in the folder opened by `client`, as synced by the
+/// client instead of loaded from the server
+async fn local_folder_child_view_names(
+  client: &TestClient,
+  workspace_id: &str,
+  parent_view_id: &str,
+) -> Vec<String> {
+  let uid = client.uid().await;
+  let encoded_folder = {
+    let lock = client.collabs[workspace_id].collab.read().await;
+    let collab: &Collab = (*lock).borrow();
+    collab
+      .encode_collab_v1(|collab| CollabType::Folder.validate_require_data(collab))
+      .unwrap()
+  };
+  let folder = Folder::from_collab_doc_state(
+    uid,
+    CollabOrigin::Empty,
+    encoded_folder.into(),
+    workspace_id,
+    vec![],
+  )
+  .unwrap();
+  folder
+    .get_views_belong_to(parent_view_id)
+    .into_iter()
+    .map(|view| view.name.clone())
+    .collect()
+}
+
+#[tokio::test]
+async fn duplicate_to_workspace_collision_policy() {
+  let client = TestClient::new_user().await;
+  let workspace_id = client.workspace_id().await;
+  let uid = client.uid().await;
+
+  // collab that already exists in the destination, e.g. from a previous import
+  let object_id = uuid::Uuid::new_v4().to_string();
+  client
+    .api_client
+    .create_collab(CreateCollabParams {
+      workspace_id: workspace_id.clone(),
+      object_id: object_id.clone(),
+      encoded_collab_v1: test_encode_collab_v1(&object_id, "title", "existing")
+        .encode_to_bytes()
+        .unwrap(),
+      collab_type: CollabType::Unknown,
+    })
+    .await
+    .unwrap();
+
+  let (pg_pool, collab_cache) = local_collab_cache().await;
+  let existing = select_blob_from_af_collab(&pg_pool, &CollabType::Unknown, &object_id)
+    .await
+    .unwrap();
+  let duplicated = test_encode_collab_v1(&object_id, "title", "duplicated")
+    .encode_to_bytes()
+    .unwrap();
+  let params = CollabParams {
+    object_id: object_id.clone(),
+    encoded_collab_v1: duplicated.clone().into(),
+    collab_type: CollabType::Unknown,
+    embeddings: None,
+  };
+
+  // error
+  let mut txn = pg_pool.begin().await.unwrap();
+  let err = insert_collab_for_duplicator(
+    &collab_cache,
+    &workspace_id,
+    &uid,
+    &params,
+    &mut txn,
+    CollisionPolicy::Error,
+  )
+  .await
+  .unwrap_err();
+  assert!(matches!(err, AppError::RecordAlreadyExists(_)), "{}", err);
+  txn.rollback().await.unwrap();
+
+  // skip, existing collab is untouched
+  let mut txn = pg_pool.begin().await.unwrap();
+  let inserted = insert_collab_for_duplicator(
+    &collab_cache,
+    &workspace_id,
+    &uid,
+    &params,
+    &mut txn,
+    CollisionPolicy::Skip,
+  )
+  .await
+  .unwrap();
+  txn.commit().await.unwrap();
+  assert!(!inserted);
+  let blob = select_blob_from_af_collab(&pg_pool, &CollabType::Unknown, &object_id)
+    .await
+    .unwrap();
+  assert_eq!(blob, existing);
+
+  // overwrite, existing collab is replaced
+  let mut txn = pg_pool.begin().await.unwrap();
+  let inserted = insert_collab_for_duplicator(
+    &collab_cache,
+    &workspace_id,
+    &uid,
+    &params,
+    &mut txn,
+    CollisionPolicy::Overwrite,
+  )
+  .await
+  .unwrap();
+  txn.commit().await.unwrap();
+  assert!(inserted);
+  let blob = select_blob_from_af_collab(&pg_pool, &CollabType::Unknown, &object_id)
+    .await
+    .unwrap();
+  assert_eq!(blob, duplicated);
+
+  // the object id belongs to another workspace, whatever the policy
+  let other_client = TestClient::new_user().await;
+  let other_workspace_id = other_client.workspace_id().await;
+  let other_object_id = uuid::Uuid::new_v4().to_string();
+  other_client
+    .api_client
+    .create_collab(CreateCollabParams {
+      workspace_id: other_workspace_id,
+      object_id: other_object_id.clone(),
+      encoded_collab_v1: test_encode_collab_v1(&other_object_id, "title", "other")
+        .encode_to_bytes()
+        .unwrap(),
+      collab_type: CollabType::Unknown,
+    })
+    .await
+    .unwrap();
+  let other_existing = select_blob_from_af_collab(&pg_pool, &CollabType::Unknown, &other_object_id)
+    .await
+    .unwrap();
+  for collision_policy in [
+    CollisionPolicy::Error,
+    CollisionPolicy::Skip,
+    CollisionPolicy::Overwrite,
+  ] {
+    let mut txn = pg_pool.begin().await.unwrap();
+    let err = insert_collab_for_duplicator(
+      &collab_cache,
+      &workspace_id,
+      &uid,
+      &CollabParams {
+        object_id: other_object_id.clone(),
+        encoded_collab_v1: duplicated.clone().into(),
+        collab_type: CollabType::Unknown,
+        embeddings: None,
+      },
+      &mut txn,
+      collision_policy,
+    )
+    .await
+    .unwrap_err();
+    assert!(err.to_string().contains("another workspace"), "{}", err);
+    txn.rollback().await.unwrap();
+  }
+  let blob = select_blob_from_af_collab(&pg_pool, &CollabType::Unknown, &other_object_id)
+    .await
+    .unwrap();
+  assert_eq!(blob, other_existing);
+}
+
+async fn local_pg_pool() -> PgPool {
+  dotenvy::dotenv().ok();
+  let config = get_configuration().unwrap();
+  PgPoolOptions::new()
+    .connect_with(config.db_settings.pg_connect_options())
+    .await
+    .unwrap()
+}
+
+/// Builds the collab cache of the local environment, it reads and writes the same postgres and
+/// redis as the server.
+async fn local_collab_cache() -> (PgPool, CollabCache) {
+  let pg_pool = local_pg_pool().await;
+  let collab_cache = CollabCache::new(redis_connection_manager().await, pg_pool.clone());
+  (pg_pool, collab_cache)
+}
+
+#[test]
+fn duplicate_to_workspace_insert_views_idempotent() {
+  let uid = 1;
+  let workspace_id = uuid::Uuid::new_v4().to_string();
+  let collab = Collab::new_with_origin(CollabOrigin::Empty, &workspace_id, vec![], false);
+  let workspace = Workspace::new(workspace_id.clone(), "workspace".to_string(), uid);
+  let mut folder = Folder::create(uid, collab, None, FolderData::new(workspace));
+
+  // same view set (with fixed ids) inserted twice, e.g. a re-run import
+  let root_view = new_test_folder_view(&uuid::Uuid::new_v4().to_string(), &workspace_id);
+  let views_to_add: HashMap<String, View> = (0..3)
+    .map(|_| {
+      let view = new_test_folder_view(&uuid::Uuid::new_v4().to_string(), &root_view.id);
+      (view.id.clone(), view)
+    })
+    .collect();
+  insert_views_to_folder(&mut folder, root_view.clone(), views_to_add.clone());
+  insert_views_to_folder(&mut folder, root_view.clone(), views_to_add.clone());
+
+  let root_count = folder
+    .get_views_belong_to(&workspace_id)
+    .iter()
+    .filter(|v| v.id == root_view.id)
+    .count();
+  assert_eq!(root_count, 1);
+
+  let children = folder.get_views_belong_to(&root_view.id);
+  assert_eq!(children.len(), views_to_add.len());
+  for view_id in views_to_add.keys() {
+    assert_eq!(children.iter().filter(|v| v.id == *view_id).count(), 1);
+    assert!(folder.get_view(view_id).is_some());
+  }
+}
+
+#[test]
+fn duplicate_to_workspace_text_map_page_mentions() {
+  let old_page_id = uuid::Uuid::new_v4().to_string();
+  let new_page_id = uuid::Uuid::new_v4().to_string();
+  let unknown_page_id = uuid::Uuid::new_v4().to_string();
+  let new_page_ids = HashMap::from([(old_page_id.clone(), new_page_id.clone())]);
+  let page_mention = |page_id: &str| {
+    serde_json::json!({
+      "insert": "$",
+      "attributes": { "mention": { "type": "page", "page_id": page_id } }
+    })
+  };
+
+  // array-shaped value, i.e. a delta
+  let array_value = serde_json::json!([
+    { "insert": "see " },
+    page_mention(&old_page_id),
+    page_mention(&unknown_page_id),
+  ])
+  .to_string();
+  let mut js_val = parse_text_map_value("array", &array_value).unwrap();
+  assert_eq!(
+    collect_page_mention_ids(&js_val),
+    vec![old_page_id.clone(), unknown_page_id.clone()]
+  );
+  remap_page_mention_ids(&mut js_val, &new_page_ids);
+  assert_eq!(
+    js_val,
+    serde_json::json!([
+      { "insert": "see " },
+      page_mention(&new_page_id),
+      page_mention(&unknown_page_id),
+    ])
+  );
+
+  // object-shaped value, with the mention nested within another delta
+  let object_value = serde_json::json!({
+    "delta": [page_mention(&old_page_id)],
+    "mention": { "type": "date", "date": "2024-01-01" },
+  })
+  .to_string();
+  let mut js_val = parse_text_map_value("object", &object_value).unwrap();
+  assert_eq!(collect_page_mention_ids(&js_val), vec![old_page_id.clone()]);
+  remap_page_mention_ids(&mut js_val, &new_page_ids);
+  assert_eq!(
+    js_val,
+    serde_json::json!({
+      "delta": [page_mention(&new_page_id)],
+      "mention": { "type": "date", "date": "2024-01-01" },
+    })
+  );
+
+  // values that can't contain any mention are left as is
+  let malformed_value = format!(r#"[{{"insert": "$", "page_id": "{}""#, old_page_id);
+  assert!(parse_text_map_value("malformed", &malformed_value).is_none());
+  assert!(parse_text_map_value("scalar", "42").is_none());
+}
+
+#[tokio::test]
+async fn collect_reachable_views_of_published_doc() {
+  let client_1 = TestClient::new_user().await;
+  let workspace_id = client_1.workspace_id().await;
+
+  // root doc mentions a published page and an unpublished one, and embeds a database
+  let root_view_id = uuid::Uuid::new_v4();
+  let published_page_view_id = uuid::Uuid::new_v4();
+  let unpublished_page_view_id = uuid::Uuid::new_v4();
+  // uuid must be fixed because it is referenced in the doc
+  let embedded_db_view_id = "bb221175-14da-4a05-a09d-595e42d2350f";
+  let root_doc_hex = doc_hex_with_page_mentions(
+    published_data::DOC_WITH_EMBEDDED_DB_HEX,
+    &[
+      published_page_view_id.to_string(),
+      unpublished_page_view_id.to_string(),
+    ],
+  );
+  // mentioning the root back must not visit it twice
+  let published_page_hex = doc_hex_with_page_mentions(
+    published_data::DOC_2_DOC_STATE_HEX,
+    &[root_view_id.to_string()],
+  );
+  client_1
+    .publish_collabs(
+      &workspace_id,
+      vec![
+        (
+          root_view_id,
+          published_data::DOC_WITH_EMBEDDED_DB_META,
+          &root_doc_hex,
+        ),
+        (
+          published_page_view_id,
+          published_data::DOC_2_META,
+          &published_page_hex,
+        ),
+        (
+          embedded_db_view_id.parse().unwrap(),
+          published_data::EMBEDDED_DB_META,
+          published_data::EMBEDDED_DB_HEX,
+        ),
+      ],
+    )
+    .await;
+
+  let pg_pool = local_pg_pool().await;
+  let reachable_views =
+    collect_reachable_published_views(&pg_pool, &root_view_id.to_string(), CollabType::Document)
+      .await
+      .unwrap();
+  assert_eq!(reachable_views.len(), 4);
+  let reachable_views: HashMap<String, ReachableView> = reachable_views
+    .into_iter()
+    .map(|v| (v.view_id.clone(), v))
+    .collect();
+
+  let expected = [
+    (
+      root_view_id.to_string(),
+      Some("docwithembeddeddb"),
+      Some(PublishedViewLayout::Document),
+    ),
+    (
+      published_page_view_id.to_string(),
+      Some("doc2"),
+      Some(PublishedViewLayout::Document),
+    ),
+    (unpublished_page_view_id.to_string(), None, None),
+    (
+      embedded_db_view_id.to_string(),
+      Some("embeddeddb"),
+      Some(PublishedViewLayout::Grid),
+    ),
+  ];
+  for (view_id, name, layout) in expected {
+    let reachable_view = &reachable_views[&view_id];
+    assert_eq!(reachable_view.name.as_deref(), name);
+    assert_eq!(reachable_view.layout, layout);
+    assert_eq!(reachable_view.is_published, name.is_some());
+  }
+
+  // the root must be published, an id that was never published is not found
+  let err = collect_reachable_published_views(
+    &pg_pool,
+    &unpublished_page_view_id.to_string(),
+    CollabType::Document,
+  )
+  .await
+  .unwrap_err();
+  assert!(err.is_record_not_found(), "{:?}", err);
+}
+
+#[tokio::test]
+async fn collect_reachable_views_of_published_database() {
+  let client_1 = TestClient::new_user().await;
+  let workspace_id = client_1.workspace_id().await;
+
+  // database with a relation column to another database
+  let db_with_rel_col_view_id = uuid::Uuid::new_v4();
+  // uuid must be fixed because it is related to the db_with_rel_col
+  let related_db_view_id = "5fc669fa-8867-4f6d-98f1-ce387597eabd";
+  // database whose row documents mention an unpublished page
+  let db_with_row_doc_view_id = uuid::Uuid::new_v4();
+  let mentioned_page_view_id = uuid::Uuid::new_v4();
+  let db_with_row_doc_hex = db_hex_with_row_doc_page_mentions(
+    published_data::DB_ROW_WITH_DOC_HEX,
+    &[mentioned_page_view_id.to_string()],
+  );
+  client_1
+    .publish_collabs(
+      &workspace_id,
+      vec![
+        (
+          db_with_rel_col_view_id,
+          published_data::DB_WITH_REL_COL_META,
+          published_data::DB_WITH_REL_COL_HEX,
+        ),
+        (
+          related_db_view_id.parse().unwrap(),
+          published_data::RELATED_DB_META,
+          published_data::RELATED_DB_HEX,
+        ),
+        (
+          db_with_row_doc_view_id,
+          published_data::DB_ROW_WITH_DOC_META,
+          &db_with_row_doc_hex,
+        ),
+      ],
+    )
+    .await;
+
+  let pg_pool = local_pg_pool().await;
+  let reachable_views = collect_reachable_published_views(
+    &pg_pool,
+    &db_with_rel_col_view_id.to_string(),
+    CollabType::Database,
+  )
+  .await
+  .unwrap();
+  let reachable_views: HashMap<String, ReachableView> = reachable_views
+    .into_iter()
+    .map(|v| (v.view_id.clone(), v))
+    .collect();
+  assert_eq!(reachable_views.len(), 2, "{:?}", reachable_views);
+  let related_db_view = &reachable_views[related_db_view_id];
+  assert_eq!(related_db_view.name.as_deref(), Some("grid2"));
+  assert_eq!(related_db_view.layout, Some(PublishedViewLayout::Grid));
+  assert!(related_db_view.is_published);
+
+  let reachable_views = collect_reachable_published_views(
+    &pg_pool,
+    &db_with_row_doc_view_id.to_string(),
+    CollabType::Database,
+  )
+  .await
+  .unwrap();
+  let mentioned_page_view = reachable_views
+    .iter()
+    .find(|v| v.view_id == mentioned_page_view_id.to_string())
+    .expect("page mentioned in a row document is not listed");
+  assert!(!mentioned_page_view.is_published);
+}
+
+#[tokio::test]
+async fn collect_reachable_views_match_duplication() {
+  let client_1 = TestClient::new_user().await;
+  let workspace_id = client_1.workspace_id().await;
+
+  // the related database is reached through the page mention and through the relation column
+  let root_view_id = uuid::Uuid::new_v4();
+  let db_with_rel_col_view_id = uuid::Uuid::new_v4();
+  // uuid must be fixed because it is related to the db_with_rel_col
+  let related_db_view_id = "5fc669fa-8867-4f6d-98f1-ce387597eabd";
+  let root_doc_hex = doc_hex_with_page_mentions(
+    published_data::DOC_2_DOC_STATE_HEX,
+    &[
+      db_with_rel_col_view_id.to_string(),
+      related_db_view_id.to_string(),
+    ],
+  );
+  client_1
+    .publish_collabs(
+      &workspace_id,
+      vec![
+        (root_view_id, published_data::DOC_2_META, &root_doc_hex),
+        (
+          db_with_rel_col_view_id,
+          published_data::DB_WITH_REL_COL_META,
+          published_data::DB_WITH_REL_COL_HEX,
+        ),
+        (
+          related_db_view_id.parse().unwrap(),
+          published_data::RELATED_DB_META,
+          published_data::RELATED_DB_HEX,
+        ),
+      ],
+    )
+    .await;
+
+  let pg_pool = local_pg_pool().await;
+  let mut reachable_names =
+    collect_reachable_published_views(&pg_pool, &root_view_id.to_string(), CollabType::Document)
+      .await
+      .unwrap()
+      .into_iter()
+      .filter_map(|v| v.name)
+      .collect::<Vec<_>>();
+  reachable_names.sort();
+
+  let client_2 = TestClient::new_user().await;
+  let workspace_id_2 = client_2.workspace_id().await;
+  let fv = client_2
+    .api_client
+    .get_workspace_folder(&workspace_id_2, Some(5), None)
+    .await
+    .unwrap();
+  let existing_view_ids = folder_view_names(&fv)
+    .into_iter()
+    .map(|(view_id, _)| view_id)
+    .collect::<HashSet<_>>();
+  client_2
+    .duplicate_published_to_workspace(&workspace_id_2, &root_view_id.to_string(), &fv.view_id)
+    .await;
+
+  let fv = client_2
+    .api_client
+    .get_workspace_folder(&workspace_id_2, Some(5), None)
+    .await
+    .unwrap();
+  let mut duplicated_names = folder_view_names(&fv)
+    .into_iter()
+    .filter(|(view_id, _)| !existing_view_ids.contains(view_id))
+    .map(|(_, name)| name)
+    .collect::<Vec<_>>();
+  duplicated_names.sort();
+  assert_eq!(reachable_names, vec!["doc2", "grid2", "grid3"]);
+  assert_eq!(reachable_names, duplicated_names);
+}
+
+/// Returns the (view id, name) of every view below `fv`
+fn folder_view_names(fv: &FolderView) -> Vec<(String, String)> {
+  fv.children
+    .iter()
+    .flat_map(|child| {
+      std::iter::once((child.view_id.clone(), child.name.clone())).chain(folder_view_names(child))
+    })
+    .collect()
+}
+
+/// Collab cache whose first `conflicts` writes fail with a serialization failure raised by
+/// postgres, as if there were concurrent writers
+struct ConflictingCollabStorage {
+  collab_cache: CollabCache,
+  conflicts: AtomicUsize,
+  /// object ids written by every attempt of the duplication, a conflict ends an attempt
+  attempts: std::sync::Mutex<Vec<Vec<String>>>,
+  /// object ids finished after their transaction is committed
+  finished_oids: std::sync::Mutex<Vec<String>>,
+}
+
+impl ConflictingCollabStorage {
+  fn new(collab_cache: CollabCache, conflicts: usize) -> Self {
+    Self {
+      collab_cache,
+      conflicts: AtomicUsize::new(conflicts),
+      attempts: std::sync::Mutex::new(vec![vec![]]),
+      finished_oids: std::sync::Mutex::new(vec![]),
+    }
+  }
+}
+
+#[async_trait]
+impl DuplicatorCollabStorage for ConflictingCollabStorage {
+  async fn get_latest_collab(
+    &self,
+    uid: i64,
+    workspace_id: &str,
+    oid: &str,
+    collab_type: CollabType,
+  ) -> Result<EncodedCollab, AppError> {
+    self
+      .collab_cache
+      .get_latest_collab(uid, workspace_id, oid, collab_type)
+      .await
+  }
+
+  async fn insert_collab_with_transaction(
+    &self,
+    workspace_id: &str,
+    uid: &i64,
+    params: &CollabParams,
+    txn: &mut Transaction<'_, Postgres>,
+    action_description: &str,
+  ) -> Result<(), AppError> {
+    self
+      .collab_cache
+      .insert_collab_with_transaction(workspace_id, uid, params, txn, action_description)
+      .await?;
+    let conflict = self
+      .conflicts
+      .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+      .is_ok();
+    {
+      let mut attempts = self.attempts.lock().unwrap();
+      attempts.last_mut().unwrap().push(params.object_id.clone());
+      if conflict {
+        attempts.push(vec![]);
+      }
+    }
+    if conflict {
+      let err = sqlx::query(
+        "DO $$ BEGIN RAISE EXCEPTION SQLSTATE '40001' USING MESSAGE = 'concurrent update'; END $$",
+      )
+      .execute(txn.deref_mut())
+      .await
+      .unwrap_err();
+      return Err(AppError::from(err));
+    }
+    Ok(())
+  }
+
+  async fn finish_committed_collab(
+    &self,
+    uid: &i64,
+    params: &CollabParams,
+  ) -> Result<(), AppError> {
+    self
+      .finished_oids
+      .lock()
+      .unwrap()
+      .push(params.object_id.clone());
+    self.collab_cache.finish_committed_collab(uid, params).await
+  }
+}
+
+#[tokio::test]
+async fn duplicate_to_workspace_retry_on_transaction_conflict() {
+  let client_1 = TestClient::new_user().await;
+  let workspace_id = client_1.workspace_id().await;
+  let doc_view_id = uuid::Uuid::new_v4();
+  client_1
+    .publish_collabs(
+      &workspace_id,
+      vec![(
+        doc_view_id,
+        published_data::DOC_2_META,
+        published_data::DOC_2_DOC_STATE_HEX,
+      )],
+    )
+    .await;
+
+  let client_2 = TestClient::new_user().await;
+  let workspace_id_2 = client_2.workspace_id().await;
+  let uid_2 = client_2.uid().await;
+  let fv = client_2
+    .api_client
+    .get_workspace_folder(&workspace_id_2, Some(5), None)
+    .await
+    .unwrap();
+  let (pg_pool, collab_cache) = local_collab_cache().await;
+
+  // conflicts twice, then succeeds
+  let storage = Arc::new(ConflictingCollabStorage::new(collab_cache.clone(), 2));
+  let progress = Arc::new(DuplicationProgress::default());
+  let root_view_id = duplicate_published_collab_to_workspace(
+    &pg_pool,
+    None,
+    storage.clone(),
+    Arc::new(NoOpGroupBroadcaster),
+    uid_2,
+    doc_view_id.to_string(),
+    workspace_id_2.clone(),
+    fv.view_id.clone(),
+    4,
+    DuplicationStrategy::Deep,
+    CollisionPolicy::Error,
+    progress.clone(),
+  )
+  .await
+  .unwrap();
+  // counted from zero again on every attempt
+  assert_eq!(progress.fraction(), 1.0);
+  let attempts = storage.attempts.lock().unwrap().clone();
+  assert_eq!(attempts.len(), 3, "{:?}", attempts);
+  let failed_oids = attempts[..2].concat();
+  let committed_oids = &attempts[2];
+  assert!(committed_oids.contains(&root_view_id));
+
+  // every attempt generates new ids, the ones of the failed attempts are rolled back
+  assert_eq!(
+    failed_oids.iter().collect::<HashSet<_>>().len(),
+    failed_oids.len()
+  );
+  for oid in &failed_oids {
+    assert!(!committed_oids.contains(oid));
+    assert!(!is_collab_exists(oid, &pg_pool).await.unwrap());
+  }
+  for oid in committed_oids {
+    assert!(is_collab_exists(oid, &pg_pool).await.unwrap());
+  }
+  // only the collabs of the committed attempt get their policies and caches
+  let finished_oids: HashSet<String> = storage
+    .finished_oids
+    .lock()
+    .unwrap()
+    .iter()
+    .cloned()
+    .collect();
+  assert_eq!(
+    finished_oids,
+    committed_oids.iter().cloned().collect::<HashSet<_>>()
+  );
+
+  // gives up after the last attempt, without writing anything
+  let storage = Arc::new(ConflictingCollabStorage::new(collab_cache, usize::MAX));
+  let err = duplicate_published_collab_to_workspace(
+    &pg_pool,
+    None,
+    storage.clone(),
+    Arc::new(NoOpGroupBroadcaster),
+    uid_2,
+    doc_view_id.to_string(),
+    workspace_id_2,
+    fv.view_id,
+    4,
+    DuplicationStrategy::Deep,
+    CollisionPolicy::Error,
+    Arc::new(DuplicationProgress::default()),
+  )
+  .await
+  .unwrap_err();
+  assert!(err.is_transaction_conflict(), "{:?}", err);
+  assert_eq!(storage.attempts.lock().unwrap().len() - 1, 3);
+  assert!(storage.finished_oids.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn retry_on_transaction_conflict_other_errors() {
+  // other errors are not retried
+  let attempts = AtomicUsize::new(0);
+  let err = retry_on_transaction_conflict(3, Duration::from_millis(10), || async {
+    attempts.fetch_add(1, Ordering::SeqCst);
+    Err::<(), _>(AppError::RecordAlreadyExists("collab".to_string()))
+  })
+  .await
+  .unwrap_err();
+  assert!(matches!(err, AppError::RecordAlreadyExists(_)));
+  assert_eq!(attempts.load(Ordering::SeqCst), 1);
+}
+
+fn new_test_folder_view(view_id: &str, parent_view_id: &str) -> View {
+  View {
+    id: view_id.to_string(),
+    parent_view_id: parent_view_id.to_string(),
+    name: view_id.to_string(),
+    children: RepeatedViewIdentifier { items: vec![] },
+    created_at: 0,
+    is_favorite: false,
+    layout: ViewLayout::Document,
+    icon: None,
+    created_by: None,
+    last_edited_time: 0,
+    last_edited_by: None,
+    extra: None,
+  }
+}
+
+#[tokio::test]
+async fn duplicate_database_rows_with_concurrency() {
+  let (database_row_collabs, dup_row_ids) = large_published_database_rows(50);
+  let new_db_id = uuid::Uuid::new_v4().to_string();
+
+  let sequential = duplicate_database_rows(
+    database_row_collabs.clone(),
+    dup_row_ids.clone(),
+    new_db_id.clone(),
+    1,
+  )
+  .await
+  .unwrap();
+  let parallel = duplicate_database_rows(
+    database_row_collabs.clone(),
+    dup_row_ids.clone(),
+    new_db_id.clone(),
+    8,
+  )
+  .await
+  .unwrap();
+
+  // row id assignment does not depend on scheduling
+  assert_eq!(sequential.len(), database_row_collabs.len());
+  assert_eq!(parallel.len(), database_row_collabs.len());
+  for (seq_row, par_row) in sequential.iter().zip(parallel.iter()) {
+    assert_eq!(seq_row.pub_row_id, par_row.pub_row_id);
+    assert_eq!(seq_row.dup_row_id, par_row.dup_row_id);
+    assert_eq!(dup_row_ids[&par_row.pub_row_id], par_row.dup_row_id);
+  }
+
+  for dup_row in parallel {
+    let encoded_collab = EncodedCollab::decode_from_bytes(&dup_row.encoded_collab).unwrap();
+    let row_collab =
+      collab_from_doc_state(encoded_collab.doc_state.to_vec(), &dup_row.dup_row_id).unwrap();
+    let row_detail = RowDetail::from_collab(&row_collab).unwrap();
+    assert_eq!(row_detail.row.id.to_string(), dup_row.dup_row_id);
+    assert_eq!(row_detail.row.database_id, new_db_id);
+  }
+}
+
+/// Replicates the rows of a published database `times` times, assigning a duplicated row id
+/// to every published row.
+fn large_published_database_rows(
+  times: usize,
+) -> (HashMap<String, Vec<u8>>, Arc<HashMap<String, String>>) {
+  let pub_db_data = serde_json::from_slice::<PublishDatabaseData>(
+    &hex::decode(published_data::GRID_1_DB_DATA).unwrap(),
+  )
+  .unwrap();
+  let mut database_row_collabs = HashMap::new();
+  for i in 0..times {
+    for (pub_row_id, row_bin_data) in &pub_db_data.database_row_collabs {
+      database_row_collabs.insert(format!("{}-{}", pub_row_id, i), row_bin_data.clone());
+    }
+  }
+  let dup_row_ids = database_row_collabs
+    .keys()
+    .map(|pub_row_id| (pub_row_id.clone(), gen_row_id().to_string()))
+    .collect();
+  (database_row_collabs, Arc::new(dup_row_ids))
+}
+
+fn get_database_id_and_row_ids(published_db_blob: &[u8]) -> (String, HashSet<String>) {
+  let pub_db_data = serde_json::from_slice::<PublishDatabaseData>(published_db_blob).unwrap();
+  let db_collab = collab_from_doc_state(pub_db_data.database_collab, "").unwrap();
+  let pub_db_id = DatabaseBody::database_id_from_collab(&db_collab).unwrap();
+  let row_ids: HashSet<String> = pub_db_data.database_row_collabs.into_keys().collect();
+  (pub_db_id, row_ids)
+}
+
+/// Re-encodes a published document so that its only page mentions are `page_ids`
+fn doc_hex_with_page_mentions(doc_state_hex: &str, page_ids: &[String]) -> String {
+  let doc_collab = collab_from_doc_state(hex::decode(doc_state_hex).unwrap(), "").unwrap();
+  let mut doc_data = Document::open(doc_collab)
+    .unwrap()
+    .get_document_data()
+    .unwrap();
+  let text_map = doc_data.meta.text_map.get_or_insert_with(HashMap::new);
+  text_map.retain(|_, delta| !delta.contains("\"mention\""));
+  let delta = page_ids
+    .iter()
+    .map(|page_id| {
+      serde_json::json!({
+        "insert": "$",
+        "attributes": { "mention": { "type": "page", "page_id": page_id } }
+      })
+    })
+    .collect::<Vec<_>>();
+  text_map.insert(
+    uuid::Uuid::new_v4().to_string(),
+    serde_json::Value::Array(delta).to_string(),
+  );
+
+  let new_doc =
+    Document::create_with_data(collab_from_doc_state(vec![], "").unwrap(), doc_data).unwrap();
+  let encoded_collab = new_doc
+    .split()
+    .0
+    .encode_collab_v1(|collab| CollabType::Document.validate_require_data(collab))
+    .unwrap();
+  hex::encode(encoded_collab.doc_state)
+}
+
+/// Replaces the page mentions of every row document of a published database with `page_ids`
+fn db_hex_with_row_doc_page_mentions(db_hex: &str, page_ids: &[String]) -> String {
+  let mut published_db: PublishDatabaseData =
+    serde_json::from_slice(&hex::decode(db_hex).unwrap()).unwrap();
+  assert!(!published_db.database_row_document_collabs.is_empty());
+  for doc_state in published_db.database_row_document_collabs.values_mut() {
+    *doc_state = hex::decode(doc_hex_with_page_mentions(
+      &hex::encode(doc_state.as_slice()),
+      page_ids,
+    ))
+    .unwrap();
+  }
+  hex::encode(serde_json::to_vec(&published_db).unwrap())
 }